@@ -2,7 +2,7 @@
 extern crate criterion;
 extern crate authorized_keys;
 
-use authorized_keys::openssh::v2::KeysFile;
+use authorized_keys::openssh::v2::{KeyAuthorizationRef, KeysFile};
 use std::str::FromStr;
 
 use criterion::{black_box, Criterion};
@@ -15,5 +15,17 @@ fn file_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, file_benchmark);
+fn borrowed_benchmark(c: &mut Criterion) {
+    c.bench_function("parse benchmark file without copying", |b| {
+        b.iter(|| {
+            for line in TEST_FILE.lines() {
+                if !line.trim_start().is_empty() && !line.trim_start().starts_with('#') {
+                    black_box(KeyAuthorizationRef::parse(line).unwrap());
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(benches, file_benchmark, borrowed_benchmark);
 criterion_main!(benches);
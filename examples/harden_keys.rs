@@ -17,12 +17,12 @@ fn main() {
         "After:\n{}",
         KeysFile::from_iter(key_file.into_iter().map(|line| {
             match line {
-                KeysFileLine::Comment(c) => KeysFileLine::Comment(c),
                 KeysFileLine::Key(key) => KeysFileLine::Key(
                     key.clear_options()
                         .option_name("restrict".to_owned())
                         .option(("command".to_owned(), Some("uptime".to_owned()))),
                 ),
+                other => other,
             }
         }))
     );
@@ -16,8 +16,8 @@ fn main() {
     println!(
         "After:\n{}",
         KeysFile::from_iter(key_file.into_iter().flat_map(|line| match line {
-            KeysFileLine::Comment(_) => None,
             KeysFileLine::Key(key) => Some(KeysFileLine::Key(key.remove_comments())),
+            _ => None,
         }))
     );
 }
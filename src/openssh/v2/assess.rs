@@ -0,0 +1,206 @@
+use super::models::{
+    KeyData, KeyDataError, KeyFamily, KeyPolicy, KeyStatus, KeyStrength, KeysFile, KeysFileLine,
+    PublicKey,
+};
+
+/// Security strength, in bits, of RSA and DSA keys of at least each
+/// size, from NIST SP 800-57 part 1.
+const MODULUS_STRENGTHS: [(usize, usize); 5] = [
+    (15360, 256),
+    (7680, 192),
+    (3072, 128),
+    (2048, 112),
+    (1024, 80),
+];
+
+impl KeyData {
+    /// Family of algorithm the key uses; a certificate is in the family
+    /// of the key it certifies.
+    #[must_use]
+    pub fn family(&self) -> KeyFamily {
+        match self {
+            KeyData::Dsa { .. } => KeyFamily::Dsa,
+            KeyData::Ecdsa { .. } | KeyData::SkEcdsa { .. } => KeyFamily::Ecdsa,
+            KeyData::Ed25519 { .. } | KeyData::SkEd25519 { .. } => KeyFamily::Ed25519,
+            KeyData::Rsa { .. } => KeyFamily::Rsa,
+            KeyData::Certificate(certificate) => certificate.key.family(),
+        }
+    }
+
+    /// Effective security of the key in bits, following NIST SP 800-57:
+    /// half the curve size for elliptic curve keys (at most 256), and
+    /// the table there for RSA and DSA keys, which gives keys under 1024
+    /// bits no security.
+    #[must_use]
+    pub fn security_bits(&self) -> usize {
+        let bits = self.bits();
+
+        match self.family() {
+            KeyFamily::Dsa | KeyFamily::Rsa => MODULUS_STRENGTHS
+                .iter()
+                .find(|&&(size, _)| bits >= size)
+                .map_or(0, |&(_, strength)| strength),
+            KeyFamily::Ecdsa | KeyFamily::Ed25519 => (bits / 2).min(256),
+        }
+    }
+}
+
+impl KeyPolicy {
+    /// Assess the strength of a key against the policy.
+    #[must_use]
+    pub fn assess(&self, key: &KeyData) -> KeyStrength {
+        let family = key.family();
+        let bits = key.bits();
+        let status = match family {
+            KeyFamily::Dsa if self.dsa_deprecated => KeyStatus::Deprecated,
+            KeyFamily::Ecdsa if self.nist_curves_deprecated => KeyStatus::Deprecated,
+            KeyFamily::Dsa | KeyFamily::Rsa if bits < self.min_modulus_bits => KeyStatus::Weak,
+            _ => KeyStatus::Ok,
+        };
+
+        KeyStrength {
+            family,
+            bits,
+            security_bits: key.security_bits(),
+            status,
+        }
+    }
+}
+
+impl PublicKey {
+    /// Assess the strength of the key against `policy`.
+    ///
+    /// ```
+    /// use authorized_keys::openssh::v2::{KeyAuthorization, KeyFamily, KeyPolicy, KeyStatus};
+    ///
+    /// let authorization: KeyAuthorization = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM"
+    ///     .parse()
+    ///     .unwrap();
+    /// let strength = authorization.key.strength(&KeyPolicy::default()).unwrap();
+    ///
+    /// assert_eq!(KeyFamily::Ed25519, strength.family);
+    /// assert_eq!(128, strength.security_bits);
+    /// assert_eq!(KeyStatus::Ok, strength.status);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the key's data cannot be decoded.
+    pub fn strength(&self, policy: &KeyPolicy) -> Result<KeyStrength, KeyDataError> {
+        Ok(policy.assess(&self.key_data()?))
+    }
+}
+
+impl KeysFile {
+    /// Keys which do not meet `policy`, with the (1-based) number of the
+    /// line each is on and its strength, or why its strength could not
+    /// be assessed.
+    #[must_use]
+    pub fn non_compliant_keys(
+        &self,
+        policy: &KeyPolicy,
+    ) -> Vec<(usize, Result<KeyStrength, KeyDataError>)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                KeysFileLine::Key(key) => match key.key.strength(policy) {
+                    Ok(KeyStrength {
+                        status: KeyStatus::Ok,
+                        ..
+                    }) => None,
+                    strength => Some((index + 1, strength)),
+                },
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{parse_key, DSA, ECDSA_384, ED25519, RSA_1024, RSA_2048};
+
+    fn strength(key: &str, policy: &KeyPolicy) -> KeyStrength {
+        parse_key(key).key.strength(policy).unwrap()
+    }
+
+    #[test]
+    fn it_assesses_keys_against_the_default_policy() {
+        let policy = KeyPolicy::default();
+        let cases = vec![
+            (DSA, KeyFamily::Dsa, 1024, 80, KeyStatus::Deprecated),
+            (ECDSA_384, KeyFamily::Ecdsa, 384, 192, KeyStatus::Ok),
+            (ED25519, KeyFamily::Ed25519, 256, 128, KeyStatus::Ok),
+            (RSA_1024, KeyFamily::Rsa, 1024, 80, KeyStatus::Weak),
+            (RSA_2048, KeyFamily::Rsa, 2048, 112, KeyStatus::Ok),
+        ];
+
+        for (key, family, bits, security_bits, status) in cases {
+            assert_eq!(
+                KeyStrength {
+                    family,
+                    bits,
+                    security_bits,
+                    status,
+                },
+                strength(key, &policy),
+                "{}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn it_assesses_keys_against_other_policies() {
+        let strict = KeyPolicy::strict();
+
+        assert_eq!(KeyStatus::Deprecated, strength(ECDSA_384, &strict).status);
+        assert_eq!(KeyStatus::Weak, strength(RSA_2048, &strict).status);
+        assert_eq!(KeyStatus::Ok, strength(ED25519, &strict).status);
+
+        let lenient = KeyPolicy {
+            min_modulus_bits: 1024,
+            dsa_deprecated: false,
+            ..KeyPolicy::default()
+        };
+
+        assert_eq!(KeyStatus::Ok, strength(DSA, &lenient).status);
+        assert_eq!(KeyStatus::Ok, strength(RSA_1024, &lenient).status);
+    }
+
+    #[test]
+    fn it_gives_effective_security_of_rsa_keys() {
+        let rsa = |size: usize| KeyData::Rsa {
+            exponent: vec![1, 0, 1],
+            modulus: vec![0xff; size / 8],
+        };
+
+        assert_eq!(0, rsa(768).security_bits());
+        assert_eq!(128, rsa(4096).security_bits());
+        assert_eq!(256, rsa(16384).security_bits());
+    }
+
+    #[test]
+    fn it_lists_non_compliant_keys() {
+        let subject = format!(
+            "# keys\n{}\n{} old\nssh-rsa foobar==\n{}\n",
+            ED25519, RSA_1024, DSA
+        )
+        .parse::<KeysFile>()
+        .unwrap();
+
+        let found = subject
+            .non_compliant_keys(&KeyPolicy::default())
+            .into_iter()
+            .map(|(line_no, strength)| (line_no, strength.map(|strength| strength.status)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(3, found.len());
+        assert_eq!((3, Ok(KeyStatus::Weak)), found[0]);
+        assert_eq!(4, found[1].0);
+        assert!(found[1].1.is_err());
+        assert_eq!((5, Ok(KeyStatus::Deprecated)), found[2]);
+    }
+}
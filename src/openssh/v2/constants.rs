@@ -8,11 +8,237 @@ string_enum!(KeyType, {
     EcdsaSha2Nistp521 = "ecdsa-sha2-nistp521",
     SshEd25519 = "ssh-ed25519",
     SshDss = "ssh-dss",
-    SshRsa = "ssh-rsa"
+    SshRsa = "ssh-rsa",
+    SkEcdsaSha2Nistp256 = "sk-ecdsa-sha2-nistp256@openssh.com",
+    SkSshEd25519 = "sk-ssh-ed25519@openssh.com",
+    EcdsaSha2Nistp256CertV01 = "ecdsa-sha2-nistp256-cert-v01@openssh.com",
+    EcdsaSha2Nistp384CertV01 = "ecdsa-sha2-nistp384-cert-v01@openssh.com",
+    EcdsaSha2Nistp521CertV01 = "ecdsa-sha2-nistp521-cert-v01@openssh.com",
+    SshEd25519CertV01 = "ssh-ed25519-cert-v01@openssh.com",
+    SshDssCertV01 = "ssh-dss-cert-v01@openssh.com",
+    SshRsaCertV01 = "ssh-rsa-cert-v01@openssh.com",
+    SkEcdsaSha2Nistp256CertV01 = "sk-ecdsa-sha2-nistp256-cert-v01@openssh.com",
+    SkSshEd25519CertV01 = "sk-ssh-ed25519-cert-v01@openssh.com"
+}, Other);
+
+string_enum!(OptionName, {
+    AgentForwarding = "agent-forwarding",
+    CertAuthority = "cert-authority",
+    Command = "command",
+    Environment = "environment",
+    ExpiryTime = "expiry-time",
+    From = "from",
+    NoAgentForwarding = "no-agent-forwarding",
+    NoPortForwarding = "no-port-forwarding",
+    NoPty = "no-pty",
+    NoTouchRequired = "no-touch-required",
+    NoUserRc = "no-user-rc",
+    NoVerifyRequired = "no-verify-required",
+    NoX11Forwarding = "no-X11-forwarding",
+    PermitListen = "permitlisten",
+    PermitOpen = "permitopen",
+    PortForwarding = "port-forwarding",
+    Principals = "principals",
+    Pty = "pty",
+    Restrict = "restrict",
+    TouchRequired = "touch-required",
+    Tunnel = "tunnel",
+    UserRc = "user-rc",
+    VerifyRequired = "verify-required",
+    X11Forwarding = "X11-forwarding"
+});
+
+#[cfg(feature = "key_encoding")]
+string_enum!(HashAlg, {
+    Md5 = "MD5",
+    Sha256 = "SHA256"
 });
 
+impl OptionName {
+    /// Look up an option by name, ignoring ASCII case as `sshd` does
+    /// (so `No-Pty` is `NoPty`).
+    #[must_use]
+    pub fn parse_ignoring_case(name: &str) -> Option<Self> {
+        Self::enum_values()
+            .into_iter()
+            .find(|option_name| option_name.matches(name))
+    }
+
+    /// Whether `name` names this option, ignoring ASCII case.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        self.to_string().eq_ignore_ascii_case(name)
+    }
+
+    /// Whether the option is written with a value (e.g. `command="..."`).
+    #[must_use]
+    pub fn takes_value(&self) -> bool {
+        match self {
+            OptionName::Command
+            | OptionName::Environment
+            | OptionName::ExpiryTime
+            | OptionName::From
+            | OptionName::PermitListen
+            | OptionName::PermitOpen
+            | OptionName::Principals
+            | OptionName::Tunnel => true,
+            _ => false,
+        }
+    }
+}
+
 impl Default for KeyType {
     fn default() -> Self {
         KeyType::SshRsa
     }
 }
+
+impl KeyType {
+    /// Whether this is an OpenSSH certificate type (e.g.
+    /// `ssh-ed25519-cert-v01@openssh.com`).
+    #[must_use]
+    pub fn is_certificate(&self) -> bool {
+        self.certified_type().is_some()
+    }
+
+    /// The type of key a certificate type certifies (e.g. `ssh-ed25519`
+    /// for `ssh-ed25519-cert-v01@openssh.com`), or the type itself if it
+    /// is not a certificate type.
+    #[must_use]
+    pub fn plain_type(&self) -> Self {
+        self.certified_type().unwrap_or_else(|| self.clone())
+    }
+
+    /// Whether this is a FIDO/U2F security key type, or a certificate
+    /// for one.
+    #[must_use]
+    pub fn is_security_key(&self) -> bool {
+        match self.plain_type() {
+            KeyType::SkEcdsaSha2Nistp256 | KeyType::SkSshEd25519 => true,
+            _ => false,
+        }
+    }
+
+    /// Name `ssh-keygen` shows for this type of key (e.g. `ED25519` or
+    /// `RSA-CERT`), or `unknown`.
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeyType::EcdsaSha2Nistp256
+            | KeyType::EcdsaSha2Nistp384
+            | KeyType::EcdsaSha2Nistp521 => "ECDSA",
+            KeyType::SshEd25519 => "ED25519",
+            KeyType::SshDss => "DSA",
+            KeyType::SshRsa => "RSA",
+            KeyType::SkEcdsaSha2Nistp256 => "ECDSA-SK",
+            KeyType::SkSshEd25519 => "ED25519-SK",
+            KeyType::EcdsaSha2Nistp256CertV01
+            | KeyType::EcdsaSha2Nistp384CertV01
+            | KeyType::EcdsaSha2Nistp521CertV01 => "ECDSA-CERT",
+            KeyType::SshEd25519CertV01 => "ED25519-CERT",
+            KeyType::SshDssCertV01 => "DSA-CERT",
+            KeyType::SshRsaCertV01 => "RSA-CERT",
+            KeyType::SkEcdsaSha2Nistp256CertV01 => "ECDSA-SK-CERT",
+            KeyType::SkSshEd25519CertV01 => "ED25519-SK-CERT",
+            KeyType::Other(_) => "unknown",
+        }
+    }
+
+    fn certified_type(&self) -> Option<Self> {
+        match self {
+            KeyType::EcdsaSha2Nistp256CertV01 => Some(KeyType::EcdsaSha2Nistp256),
+            KeyType::EcdsaSha2Nistp384CertV01 => Some(KeyType::EcdsaSha2Nistp384),
+            KeyType::EcdsaSha2Nistp521CertV01 => Some(KeyType::EcdsaSha2Nistp521),
+            KeyType::SshEd25519CertV01 => Some(KeyType::SshEd25519),
+            KeyType::SshDssCertV01 => Some(KeyType::SshDss),
+            KeyType::SshRsaCertV01 => Some(KeyType::SshRsa),
+            KeyType::SkEcdsaSha2Nistp256CertV01 => Some(KeyType::SkEcdsaSha2Nistp256),
+            KeyType::SkSshEd25519CertV01 => Some(KeyType::SkSshEd25519),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyType, OptionName};
+
+    #[test]
+    fn it_identifies_certificate_types() {
+        let certificates = KeyType::enum_values()
+            .into_iter()
+            .filter(KeyType::is_certificate)
+            .collect::<Vec<_>>();
+
+        assert_eq!(8, certificates.len());
+        for certificate in certificates {
+            let plain = certificate.plain_type();
+
+            assert!(!plain.is_certificate());
+            assert_eq!(
+                certificate.to_string(),
+                plain.to_string().replace("@openssh.com", "") + "-cert-v01@openssh.com"
+            );
+        }
+    }
+
+    #[test]
+    fn it_looks_up_options_ignoring_case() {
+        assert_eq!(
+            Some(OptionName::NoPty),
+            OptionName::parse_ignoring_case("No-Pty")
+        );
+        assert_eq!(
+            Some(OptionName::X11Forwarding),
+            OptionName::parse_ignoring_case("x11-forwarding")
+        );
+        assert_eq!(None, OptionName::parse_ignoring_case("no-ptys"));
+        assert_eq!(Err(()), "No-Pty".parse::<OptionName>());
+    }
+
+    #[test]
+    fn it_labels_key_types_as_ssh_keygen_does() {
+        assert_eq!("ECDSA", KeyType::EcdsaSha2Nistp384.label());
+        assert_eq!("ED25519-SK-CERT", KeyType::SkSshEd25519CertV01.label());
+        assert_eq!("unknown", KeyType::Other("ssh-foo".to_owned()).label());
+        for key_type in KeyType::enum_values() {
+            if key_type.is_certificate() {
+                assert_eq!(
+                    format!("{}-CERT", key_type.plain_type().label()),
+                    key_type.label()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_identifies_security_keys() {
+        assert!(KeyType::SkSshEd25519.is_security_key());
+        assert!(KeyType::SkEcdsaSha2Nistp256CertV01.is_security_key());
+        assert!(!KeyType::EcdsaSha2Nistp256.is_security_key());
+        assert!(!KeyType::Other("sk-foo".to_owned()).is_security_key());
+    }
+
+    #[test]
+    fn it_keeps_plain_types() {
+        assert!(!KeyType::SshEd25519.is_certificate());
+        assert_eq!(KeyType::SshEd25519, KeyType::SshEd25519.plain_type());
+    }
+
+    #[test]
+    fn it_keeps_unknown_types() {
+        let subject = "ssh-xmss@openssh.com".parse::<KeyType>();
+
+        assert_eq!(
+            Ok(KeyType::Other("ssh-xmss@openssh.com".to_owned())),
+            subject
+        );
+        assert_eq!("ssh-xmss@openssh.com", subject.unwrap().to_string());
+        assert!(!KeyType::enum_values()
+            .iter()
+            .any(|key_type| match key_type {
+                KeyType::Other(_) => true,
+                _ => false,
+            }));
+    }
+}
@@ -0,0 +1,362 @@
+use super::constants::OptionName;
+use super::display::can_escape;
+use super::errors::OptionError;
+use super::models::{ExpiryTime, ForwardSpec, KeyOption, TypedKeyOption};
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Largest tunnel device number `sshd` accepts.
+const TUNNEL_MAX: u32 = 0x7fff_fffd;
+
+/// Check an option which takes no value wasn't given one.
+fn flag(
+    name: String,
+    value: Option<String>,
+    option: TypedKeyOption,
+) -> Result<TypedKeyOption, OptionError> {
+    match value {
+        None => Ok(option),
+        Some(value) => Err(OptionError::ValueNotAllowed { name, value }),
+    }
+}
+
+/// Check an option which takes a value was given one, which can be
+/// written out.
+fn required(name: &str, value: Option<String>) -> Result<String, OptionError> {
+    let value = value.ok_or_else(|| OptionError::MissingValue {
+        name: name.to_owned(),
+    })?;
+
+    if can_escape(&value) {
+        Ok(value)
+    } else {
+        Err(OptionError::InvalidValue {
+            name: name.to_owned(),
+            value,
+        })
+    }
+}
+
+/// Whether `sshd` accepts `name` as the name of an environment variable:
+/// it must be made up of ASCII letters, digits and underscores.
+pub(crate) fn is_env_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+fn environment(name: &str, value: String) -> Result<TypedKeyOption, OptionError> {
+    match value.find('=') {
+        Some(separator) if is_env_name(&value[..separator]) => Ok(TypedKeyOption::Environment {
+            name: value[..separator].to_owned(),
+            value: value[separator + 1..].to_owned(),
+        }),
+        _ => Err(OptionError::InvalidValue {
+            name: name.to_owned(),
+            value,
+        }),
+    }
+}
+
+fn from(name: &str, value: String) -> Result<TypedKeyOption, OptionError> {
+    match value.parse() {
+        Ok(patterns) => Ok(TypedKeyOption::From(patterns)),
+        Err(_) => Err(OptionError::InvalidValue {
+            name: name.to_owned(),
+            value,
+        }),
+    }
+}
+
+fn tunnel(name: &str, value: String) -> Result<TypedKeyOption, OptionError> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(TypedKeyOption::Tunnel(None));
+    }
+
+    match value.parse::<u32>() {
+        Ok(device) if device <= TUNNEL_MAX => Ok(TypedKeyOption::Tunnel(Some(device))),
+        _ => Err(OptionError::InvalidValue {
+            name: name.to_owned(),
+            value,
+        }),
+    }
+}
+
+impl TryFrom<KeyOption> for TypedKeyOption {
+    type Error = OptionError;
+
+    /// Interpret an option, failing if a known option has a missing or
+    /// invalid value. Names are matched ignoring case, as `sshd` does.
+    /// Options unknown to `sshd` are kept as `Unknown`.
+    fn try_from((name, value): KeyOption) -> Result<Self, Self::Error> {
+        let option_name = match OptionName::parse_ignoring_case(&name) {
+            Some(option_name) => option_name,
+            None => return Ok(TypedKeyOption::Unknown(name, value)),
+        };
+
+        match option_name {
+            OptionName::AgentForwarding => flag(name, value, TypedKeyOption::AgentForwarding),
+            OptionName::CertAuthority => flag(name, value, TypedKeyOption::CertAuthority),
+            OptionName::NoAgentForwarding => flag(name, value, TypedKeyOption::NoAgentForwarding),
+            OptionName::NoPortForwarding => flag(name, value, TypedKeyOption::NoPortForwarding),
+            OptionName::NoPty => flag(name, value, TypedKeyOption::NoPty),
+            OptionName::NoTouchRequired => flag(name, value, TypedKeyOption::NoTouchRequired),
+            OptionName::NoUserRc => flag(name, value, TypedKeyOption::NoUserRc),
+            OptionName::NoVerifyRequired => flag(name, value, TypedKeyOption::NoVerifyRequired),
+            OptionName::NoX11Forwarding => flag(name, value, TypedKeyOption::NoX11Forwarding),
+            OptionName::PortForwarding => flag(name, value, TypedKeyOption::PortForwarding),
+            OptionName::Pty => flag(name, value, TypedKeyOption::Pty),
+            OptionName::Restrict => flag(name, value, TypedKeyOption::Restrict),
+            OptionName::TouchRequired => flag(name, value, TypedKeyOption::TouchRequired),
+            OptionName::UserRc => flag(name, value, TypedKeyOption::UserRc),
+            OptionName::VerifyRequired => flag(name, value, TypedKeyOption::VerifyRequired),
+            OptionName::X11Forwarding => flag(name, value, TypedKeyOption::X11Forwarding),
+            OptionName::Command => required(&name, value).map(TypedKeyOption::Command),
+            OptionName::Environment => environment(&name, required(&name, value)?),
+            OptionName::ExpiryTime => {
+                let value = required(&name, value)?;
+
+                match value.parse() {
+                    Ok(time) => Ok(TypedKeyOption::ExpiryTime(time)),
+                    Err(_) => Err(OptionError::InvalidValue { name, value }),
+                }
+            }
+            OptionName::From => from(&name, required(&name, value)?),
+            OptionName::PermitListen => {
+                let value = required(&name, value)?;
+
+                match ForwardSpec::parse_listen(&value) {
+                    Ok(spec) => Ok(TypedKeyOption::PermitListen(spec)),
+                    Err(_) => Err(OptionError::InvalidValue { name, value }),
+                }
+            }
+            OptionName::PermitOpen => {
+                let value = required(&name, value)?;
+
+                match value.parse() {
+                    Ok(spec) => Ok(TypedKeyOption::PermitOpen(spec)),
+                    Err(_) => Err(OptionError::InvalidValue { name, value }),
+                }
+            }
+            OptionName::Principals => required(&name, value).map(|value| {
+                TypedKeyOption::Principals(value.split(',').map(str::to_owned).collect())
+            }),
+            OptionName::Tunnel => tunnel(&name, required(&name, value)?),
+        }
+    }
+}
+
+impl From<TypedKeyOption> for KeyOption {
+    /// Write an option in its canonical form: the name in lower case
+    /// (bar `X11`) and the value as `Display` writes it. This may differ
+    /// from the text the option was parsed from (e.g. `tunnel="007"`
+    /// becomes `tunnel="7"`), but `sshd` reads it the same way.
+    fn from(option: TypedKeyOption) -> Self {
+        let (name, value) = match option {
+            TypedKeyOption::AgentForwarding => (OptionName::AgentForwarding, None),
+            TypedKeyOption::CertAuthority => (OptionName::CertAuthority, None),
+            TypedKeyOption::NoAgentForwarding => (OptionName::NoAgentForwarding, None),
+            TypedKeyOption::NoPortForwarding => (OptionName::NoPortForwarding, None),
+            TypedKeyOption::NoPty => (OptionName::NoPty, None),
+            TypedKeyOption::NoTouchRequired => (OptionName::NoTouchRequired, None),
+            TypedKeyOption::NoUserRc => (OptionName::NoUserRc, None),
+            TypedKeyOption::NoVerifyRequired => (OptionName::NoVerifyRequired, None),
+            TypedKeyOption::NoX11Forwarding => (OptionName::NoX11Forwarding, None),
+            TypedKeyOption::PortForwarding => (OptionName::PortForwarding, None),
+            TypedKeyOption::Pty => (OptionName::Pty, None),
+            TypedKeyOption::Restrict => (OptionName::Restrict, None),
+            TypedKeyOption::TouchRequired => (OptionName::TouchRequired, None),
+            TypedKeyOption::UserRc => (OptionName::UserRc, None),
+            TypedKeyOption::VerifyRequired => (OptionName::VerifyRequired, None),
+            TypedKeyOption::X11Forwarding => (OptionName::X11Forwarding, None),
+            TypedKeyOption::Command(command) => (OptionName::Command, Some(command)),
+            TypedKeyOption::Environment { name, value } => {
+                (OptionName::Environment, Some(format!("{}={}", name, value)))
+            }
+            TypedKeyOption::ExpiryTime(time) => (OptionName::ExpiryTime, Some(time.to_string())),
+            TypedKeyOption::From(patterns) => (OptionName::From, Some(patterns.to_string())),
+            TypedKeyOption::PermitListen(spec) => {
+                (OptionName::PermitListen, Some(spec.to_string()))
+            }
+            TypedKeyOption::PermitOpen(spec) => (OptionName::PermitOpen, Some(spec.to_string())),
+            TypedKeyOption::Principals(principals) => {
+                (OptionName::Principals, Some(principals.join(",")))
+            }
+            TypedKeyOption::Tunnel(device) => (
+                OptionName::Tunnel,
+                Some(device.map_or_else(|| "any".to_owned(), |device| device.to_string())),
+            ),
+            TypedKeyOption::Unknown(name, value) => return (name, value),
+        };
+
+        (name.to_string(), value)
+    }
+}
+
+/// Times are marked as UTC, to the second; times before 1970 become
+/// the start of 1970.
+impl From<SystemTime> for ExpiryTime {
+    fn from(time: SystemTime) -> Self {
+        Self {
+            unix_time: time
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or(0),
+            utc: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(name: &str, value: Option<&str>) -> KeyOption {
+        (name.to_owned(), value.map(str::to_owned))
+    }
+
+    #[test]
+    fn it_converts_canonical_options_back_unchanged() {
+        let cases = vec![
+            option("agent-forwarding", None),
+            option("cert-authority", None),
+            option("command", Some("uptime")),
+            option("environment", Some("PATH=/bin:/usr/bin")),
+            option("environment", Some("EMPTY=")),
+            option("environment", Some("_9=x")),
+            option("expiry-time", Some("20300101")),
+            option("expiry-time", Some("203001011230Z")),
+            option("expiry-time", Some("20300101123059")),
+            option("from", Some("10.0.0.0/8,!10.1.2.3")),
+            option("no-agent-forwarding", None),
+            option("no-port-forwarding", None),
+            option("no-pty", None),
+            option("no-touch-required", None),
+            option("no-user-rc", None),
+            option("no-verify-required", None),
+            option("no-X11-forwarding", None),
+            option("permitlisten", Some("localhost:8080")),
+            option("permitopen", Some("example.com:443")),
+            option("permitopen", Some("[2001:db8::1]:*")),
+            option("port-forwarding", None),
+            option("principals", Some("alice,bob")),
+            option("pty", None),
+            option("restrict", None),
+            option("touch-required", None),
+            option("tunnel", Some("3")),
+            option("tunnel", Some("any")),
+            option("user-rc", None),
+            option("verify-required", None),
+            option("X11-forwarding", None),
+            option("comand", Some("uptime")),
+            option("x-vendor-flag", None),
+        ];
+
+        for case in cases {
+            let typed = TypedKeyOption::try_from(case.clone()).unwrap();
+
+            assert_eq!(case, KeyOption::from(typed));
+        }
+    }
+
+    #[test]
+    fn it_converts_other_forms_to_the_canonical_form() {
+        let cases = vec![
+            (option("No-Pty", None), option("no-pty", None)),
+            (
+                option("permitlisten", Some("8080")),
+                option("permitlisten", Some("*:8080")),
+            ),
+            (
+                option("permitopen", Some("host/22")),
+                option("permitopen", Some("host:22")),
+            ),
+            (
+                option("expiry-time", Some("20300101000000Z")),
+                option("expiry-time", Some("20300101Z")),
+            ),
+            (
+                option("expiry-time", Some("20300101UTC")),
+                option("expiry-time", Some("20300101Z")),
+            ),
+            (option("tunnel", Some("007")), option("tunnel", Some("7"))),
+            (option("tunnel", Some("ANY")), option("tunnel", Some("any"))),
+        ];
+
+        for (case, expected) in cases {
+            let typed = TypedKeyOption::try_from(case).unwrap();
+            let converted = KeyOption::from(typed.clone());
+
+            assert_eq!(expected, converted);
+            assert_eq!(Ok(typed), TypedKeyOption::try_from(converted));
+        }
+    }
+
+    #[test]
+    fn it_parses_option_values() {
+        assert_eq!(
+            Ok(TypedKeyOption::Environment {
+                name: "A".to_owned(),
+                value: "b=c".to_owned(),
+            }),
+            TypedKeyOption::try_from(option("environment", Some("A=b=c")))
+        );
+        assert_eq!(
+            Ok(TypedKeyOption::Principals(vec![
+                "alice".to_owned(),
+                "bob".to_owned()
+            ])),
+            TypedKeyOption::try_from(option("principals", Some("alice,bob")))
+        );
+        assert_eq!(
+            Ok(TypedKeyOption::NoPty),
+            TypedKeyOption::try_from(option("No-Pty", None))
+        );
+        assert_eq!(
+            Ok(TypedKeyOption::Tunnel(None)),
+            TypedKeyOption::try_from(option("tunnel", Some("ANY")))
+        );
+        assert_eq!(
+            Ok(TypedKeyOption::Unknown("comand".to_owned(), None)),
+            TypedKeyOption::try_from(option("comand", None))
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_options() {
+        assert_eq!(
+            Err(OptionError::ValueNotAllowed {
+                name: "no-agent-forwarding".to_owned(),
+                value: "x".to_owned(),
+            }),
+            TypedKeyOption::try_from(option("no-agent-forwarding", Some("x")))
+        );
+        assert_eq!(
+            Err(OptionError::MissingValue {
+                name: "command".to_owned(),
+            }),
+            TypedKeyOption::try_from(option("command", None))
+        );
+        for &(name, value) in &[
+            ("environment", "PATH"),
+            ("environment", "=value"),
+            ("environment", "LC-ALL=C"),
+            ("from", "10.0.0.0/8,,host"),
+            ("permitopen", "example.com"),
+            ("expiry-time", "2030-01-01"),
+            ("permitlisten", "localhost:0"),
+            ("tunnel", "tun0"),
+            ("tunnel", "2147483646"),
+            ("command", "dir C:\\"),
+        ] {
+            assert_eq!(
+                Err(OptionError::InvalidValue {
+                    name: name.to_owned(),
+                    value: value.to_owned(),
+                }),
+                TypedKeyOption::try_from(option(name, Some(value)))
+            );
+        }
+    }
+}
@@ -0,0 +1,538 @@
+use super::models::{
+    Certificate, CertificateType, KeyData, KeyDataError, KeyType, KeysFile, KeysFileLine, PublicKey,
+};
+use data_encoding::BASE64;
+use std::convert::TryFrom;
+use std::str;
+
+/// Reads the fields of SSH wire format data (RFC 4251, section 5) in
+/// turn, naming the field being read in any error.
+struct Reader<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { rest: data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest.is_empty()
+    }
+
+    fn take(&mut self, len: usize, field: &str) -> Result<&'a [u8], KeyDataError> {
+        if self.rest.len() < len {
+            return Err(KeyDataError::Truncated(field.to_owned()));
+        }
+
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+
+        Ok(taken)
+    }
+
+    fn uint32(&mut self, field: &str) -> Result<u32, KeyDataError> {
+        let bytes = self.take(4, field)?;
+
+        Ok(u32::from_be_bytes(<[u8; 4]>::try_from(bytes).unwrap()))
+    }
+
+    fn uint64(&mut self, field: &str) -> Result<u64, KeyDataError> {
+        let bytes = self.take(8, field)?;
+
+        Ok(u64::from_be_bytes(<[u8; 8]>::try_from(bytes).unwrap()))
+    }
+
+    fn bytes(&mut self, field: &str) -> Result<&'a [u8], KeyDataError> {
+        let len = self.uint32(field)?;
+
+        self.take(len as usize, field)
+    }
+
+    fn string(&mut self, field: &str) -> Result<String, KeyDataError> {
+        str::from_utf8(self.bytes(field)?)
+            .map(str::to_owned)
+            .map_err(|_| KeyDataError::InvalidField(field.to_owned()))
+    }
+
+    /// Read a non-negative multiple precision integer, without its
+    /// leading zeroes.
+    fn mpint(&mut self, field: &str) -> Result<Vec<u8>, KeyDataError> {
+        let bytes = self.bytes(field)?;
+
+        if bytes.first().map_or(false, |byte| byte & 0x80 != 0) {
+            return Err(KeyDataError::InvalidField(field.to_owned()));
+        }
+
+        Ok(bytes
+            .iter()
+            .skip_while(|&&byte| byte == 0)
+            .cloned()
+            .collect())
+    }
+
+    fn ed25519_key(&mut self) -> Result<[u8; 32], KeyDataError> {
+        <[u8; 32]>::try_from(self.bytes("public key")?)
+            .map_err(|_| KeyDataError::InvalidField("public key".to_owned()))
+    }
+
+    /// Read an ECDSA curve name, which must be the curve named by the
+    /// key type.
+    fn curve(&mut self, key_type: &KeyType) -> Result<String, KeyDataError> {
+        let curve = self.string("curve")?;
+
+        if Some(curve.as_str()) == curve_name(key_type) {
+            Ok(curve)
+        } else {
+            Err(KeyDataError::InvalidField("curve".to_owned()))
+        }
+    }
+
+    /// Read the fields of a list packed into a single string.
+    fn list<T, F>(&mut self, field: &str, mut item: F) -> Result<Vec<T>, KeyDataError>
+    where
+        F: FnMut(&mut Reader<'a>) -> Result<T, KeyDataError>,
+    {
+        let mut list = Reader::new(self.bytes(field)?);
+        let mut items = Vec::new();
+
+        while !list.is_empty() {
+            items.push(item(&mut list)?);
+        }
+
+        Ok(items)
+    }
+}
+
+/// Name of the curve an ECDSA key type uses, as written in its data.
+fn curve_name(key_type: &KeyType) -> Option<&'static str> {
+    match key_type {
+        KeyType::EcdsaSha2Nistp256 | KeyType::SkEcdsaSha2Nistp256 => Some("nistp256"),
+        KeyType::EcdsaSha2Nistp384 => Some("nistp384"),
+        KeyType::EcdsaSha2Nistp521 => Some("nistp521"),
+        _ => None,
+    }
+}
+
+/// Read the fields of a plain (not certificate) key following its type.
+fn key_fields(reader: &mut Reader, key_type: &KeyType) -> Result<KeyData, KeyDataError> {
+    match key_type {
+        KeyType::SshDss => Ok(KeyData::Dsa {
+            p: reader.mpint("p")?,
+            q: reader.mpint("q")?,
+            g: reader.mpint("g")?,
+            y: reader.mpint("y")?,
+        }),
+        KeyType::EcdsaSha2Nistp256 | KeyType::EcdsaSha2Nistp384 | KeyType::EcdsaSha2Nistp521 => {
+            Ok(KeyData::Ecdsa {
+                curve: reader.curve(key_type)?,
+                point: reader.bytes("public point")?.to_vec(),
+            })
+        }
+        KeyType::SshEd25519 => Ok(KeyData::Ed25519 {
+            key: reader.ed25519_key()?,
+        }),
+        KeyType::SshRsa => Ok(KeyData::Rsa {
+            exponent: reader.mpint("exponent")?,
+            modulus: reader.mpint("modulus")?,
+        }),
+        KeyType::SkEcdsaSha2Nistp256 => Ok(KeyData::SkEcdsa {
+            curve: reader.curve(key_type)?,
+            point: reader.bytes("public point")?.to_vec(),
+            application: reader.string("application")?,
+        }),
+        KeyType::SkSshEd25519 => Ok(KeyData::SkEd25519 {
+            key: reader.ed25519_key()?,
+            application: reader.string("application")?,
+        }),
+        _ => Err(KeyDataError::UnsupportedType(key_type.to_string())),
+    }
+}
+
+/// Read the fields of a certificate following its type.
+fn certificate_fields(reader: &mut Reader, key_type: &KeyType) -> Result<KeyData, KeyDataError> {
+    let nonce = reader.bytes("nonce")?.to_vec();
+    let key = key_fields(reader, &key_type.plain_type())?;
+    let serial = reader.uint64("serial")?;
+    let cert_type = match reader.uint32("certificate type")? {
+        1 => CertificateType::User,
+        2 => CertificateType::Host,
+        _ => return Err(KeyDataError::InvalidField("certificate type".to_owned())),
+    };
+    let key_id = reader.string("key id")?;
+    let principals = reader.list("principals", |list| list.string("principals"))?;
+    let valid_after = reader.uint64("valid after time")?;
+    let valid_before = reader.uint64("valid before time")?;
+    let critical_options = reader.list("critical options", |list| {
+        Ok((
+            list.string("critical options")?,
+            list.bytes("critical options")?.to_vec(),
+        ))
+    })?;
+    let extensions = reader.list("extensions", |list| {
+        Ok((
+            list.string("extensions")?,
+            list.bytes("extensions")?.to_vec(),
+        ))
+    })?;
+    reader.bytes("reserved")?;
+    let signature_key = reader.bytes("signature key")?.to_vec();
+    let signature = reader.bytes("signature")?.to_vec();
+
+    Ok(KeyData::Certificate(Box::new(Certificate {
+        nonce,
+        key,
+        serial,
+        cert_type,
+        key_id,
+        principals,
+        valid_after,
+        valid_before,
+        critical_options,
+        extensions,
+        signature_key,
+        signature,
+    })))
+}
+
+/// Read the fields of a key or certificate following its type.
+fn fields(reader: &mut Reader, key_type: &KeyType) -> Result<KeyData, KeyDataError> {
+    if key_type.is_certificate() {
+        certificate_fields(reader, key_type)
+    } else {
+        key_fields(reader, key_type)
+    }
+}
+
+impl KeyData {
+    /// Decode the binary data of a public key, which names its own type.
+    /// Any data following the key is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the data is truncated or invalid, or
+    /// is for a type of key which cannot be decoded.
+    pub fn from_blob(blob: &[u8]) -> Result<Self, KeyDataError> {
+        let mut reader = Reader::new(blob);
+        let key_type = KeyType::from(reader.string("key type")?.as_str());
+
+        fields(&mut reader, &key_type)
+    }
+}
+
+impl PublicKey {
+    pub(crate) fn blob(&self) -> Result<Vec<u8>, KeyDataError> {
+        BASE64
+            .decode(self.encoded_key.as_bytes())
+            .map_err(KeyDataError::InvalidBase64)
+    }
+
+    /// Data of the key as OpenSSH fingerprints it: for a certificate,
+    /// only the key it certifies, written as a plain key of that type.
+    pub(crate) fn plain_blob(&self) -> Result<Vec<u8>, KeyDataError> {
+        let blob = self.blob()?;
+        let mut reader = Reader::new(&blob);
+        let key_type = KeyType::from(reader.string("key type")?.as_str());
+
+        if !key_type.is_certificate() {
+            return Ok(blob);
+        }
+
+        reader.bytes("nonce")?;
+        let key = reader.rest;
+        key_fields(&mut reader, &key_type.plain_type())?;
+        let key = &key[..key.len() - reader.rest.len()];
+
+        let plain_type = key_type.plain_type().to_string();
+        let mut plain = u32::try_from(plain_type.len())
+            .map_err(|_| KeyDataError::InvalidField("key type".to_owned()))?
+            .to_be_bytes()
+            .to_vec();
+        plain.extend_from_slice(plain_type.as_bytes());
+        plain.extend_from_slice(key);
+
+        Ok(plain)
+    }
+
+    /// Public key, decoded into its components; see `KeyData`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the key is not valid base64, or its
+    /// data cannot be decoded.
+    pub fn key_data(&self) -> Result<KeyData, KeyDataError> {
+        KeyData::from_blob(&self.blob()?)
+    }
+
+    /// Public key, decoded into its components as `key_data` does, but
+    /// also checking that the data is for the declared `key_type` and
+    /// has nothing after the key, as `sshd` does. This catches keys
+    /// which were pasted incorrectly.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the key cannot be decoded, is for a
+    /// different type of key, or is followed by other data.
+    pub fn validate(&self) -> Result<KeyData, KeyDataError> {
+        let blob = self.blob()?;
+        let mut reader = Reader::new(&blob);
+        let embedded = reader.string("key type")?;
+        let declared = self.key_type.to_string();
+
+        if embedded != declared {
+            return Err(KeyDataError::TypeMismatch { declared, embedded });
+        }
+
+        let key_data = fields(&mut reader, &self.key_type)?;
+
+        if reader.is_empty() {
+            Ok(key_data)
+        } else {
+            Err(KeyDataError::TrailingBytes(reader.rest.len()))
+        }
+    }
+}
+
+impl KeysFile {
+    /// Keys which fail `PublicKey::validate`, with the (1-based) number
+    /// of the line each is on and why it is invalid.
+    #[must_use]
+    pub fn invalid_key_data(&self) -> Vec<(usize, KeyDataError)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                KeysFileLine::Key(key) => key.key.validate().err().map(|error| (index + 1, error)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ED25519, ED25519_DATA};
+
+    /// Encode `fields` as SSH wire format strings.
+    fn blob(fields: &[&[u8]]) -> Vec<u8> {
+        let mut blob = Vec::new();
+
+        for field in fields {
+            blob.extend_from_slice(&u32::try_from(field.len()).unwrap().to_be_bytes());
+            blob.extend_from_slice(field);
+        }
+
+        blob
+    }
+
+    #[test]
+    fn it_decodes_ed25519_keys() {
+        let subject = PublicKey::new(KeyType::SshEd25519, ED25519_DATA.to_owned());
+
+        match subject.key_data() {
+            Ok(KeyData::Ed25519 { key }) => {
+                assert_eq!([0x68, 0x2a, 0xa3, 0x5a], key[..4]);
+                assert_eq!(0x0c, key[31]);
+            }
+            other => panic!("unexpected key data: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_decodes_rsa_and_dsa_keys() {
+        assert_eq!(
+            Ok(KeyData::Rsa {
+                exponent: vec![1, 0, 1],
+                modulus: vec![0xc0, 0xff, 0xee],
+            }),
+            KeyData::from_blob(&blob(&[b"ssh-rsa", &[1, 0, 1], &[0, 0xc0, 0xff, 0xee]]))
+        );
+        assert_eq!(
+            Ok(KeyData::Dsa {
+                p: vec![7],
+                q: vec![3],
+                g: vec![2],
+                y: vec![5],
+            }),
+            KeyData::from_blob(&blob(&[b"ssh-dss", &[7], &[3], &[2], &[0, 5]]))
+        );
+    }
+
+    #[test]
+    fn it_decodes_ecdsa_and_security_keys() {
+        assert_eq!(
+            Ok(KeyData::Ecdsa {
+                curve: "nistp384".to_owned(),
+                point: vec![4, 1, 2],
+            }),
+            KeyData::from_blob(&blob(&[b"ecdsa-sha2-nistp384", b"nistp384", &[4, 1, 2]]))
+        );
+        assert_eq!(
+            Ok(KeyData::SkEcdsa {
+                curve: "nistp256".to_owned(),
+                point: vec![4, 1, 2],
+                application: "ssh:".to_owned(),
+            }),
+            KeyData::from_blob(&blob(&[
+                b"sk-ecdsa-sha2-nistp256@openssh.com",
+                b"nistp256",
+                &[4, 1, 2],
+                b"ssh:"
+            ]))
+        );
+        assert_eq!(
+            Ok(KeyData::SkEd25519 {
+                key: [9; 32],
+                application: "ssh:".to_owned(),
+            }),
+            KeyData::from_blob(&blob(&[b"sk-ssh-ed25519@openssh.com", &[9; 32], b"ssh:"]))
+        );
+    }
+
+    #[test]
+    fn it_decodes_certificates() {
+        let mut data = blob(&[b"ssh-ed25519-cert-v01@openssh.com", b"nonce", &[9; 32]]);
+        data.extend_from_slice(&42_u64.to_be_bytes());
+        data.extend_from_slice(&1_u32.to_be_bytes());
+        data.extend(blob(&[b"alice@example.com", &blob(&[b"alice", b"root"])]));
+        data.extend_from_slice(&0_u64.to_be_bytes());
+        data.extend_from_slice(&u64::max_value().to_be_bytes());
+        data.extend(blob(&[
+            &blob(&[b"force-command", &blob(&[b"uptime"])]),
+            &blob(&[b"permit-pty", b""]),
+            b"",
+            b"ca key",
+            b"signature",
+        ]));
+
+        let certificate = match KeyData::from_blob(&data) {
+            Ok(KeyData::Certificate(certificate)) => certificate,
+            other => panic!("unexpected key data: {:?}", other),
+        };
+
+        assert_eq!(b"nonce".to_vec(), certificate.nonce);
+        assert_eq!(KeyData::Ed25519 { key: [9; 32] }, certificate.key);
+        assert_eq!(42, certificate.serial);
+        assert_eq!(CertificateType::User, certificate.cert_type);
+        assert_eq!("alice@example.com", certificate.key_id);
+        assert_eq!(
+            vec!["alice".to_owned(), "root".to_owned()],
+            certificate.principals
+        );
+        assert_eq!(u64::max_value(), certificate.valid_before);
+        assert_eq!(
+            vec![("force-command".to_owned(), blob(&[b"uptime"]))],
+            certificate.critical_options
+        );
+        assert_eq!(
+            vec![("permit-pty".to_owned(), Vec::new())],
+            certificate.extensions
+        );
+        assert_eq!(b"signature".to_vec(), certificate.signature);
+    }
+
+    #[test]
+    fn it_reports_invalid_key_data() {
+        assert_eq!(
+            Err(KeyDataError::Truncated("modulus".to_owned())),
+            KeyData::from_blob(&blob(&[b"ssh-rsa", &[1, 0, 1]]))
+        );
+        assert_eq!(
+            Err(KeyDataError::Truncated("key type".to_owned())),
+            KeyData::from_blob(&[0, 0, 0, 7, b's'])
+        );
+        assert_eq!(
+            Err(KeyDataError::InvalidField("public key".to_owned())),
+            KeyData::from_blob(&blob(&[b"ssh-ed25519", &[9; 31]]))
+        );
+        for curve in &[&b"nistp384"[..], b"nistp25", b"sha2", b""] {
+            assert_eq!(
+                Err(KeyDataError::InvalidField("curve".to_owned())),
+                KeyData::from_blob(&blob(&[b"ecdsa-sha2-nistp256", curve, &[4]]))
+            );
+        }
+        assert_eq!(
+            Err(KeyDataError::InvalidField("curve".to_owned())),
+            PublicKey::new(
+                KeyType::SkEcdsaSha2Nistp256,
+                BASE64.encode(&blob(&[
+                    b"sk-ecdsa-sha2-nistp256@openssh.com",
+                    b"nistp25",
+                    &[4],
+                    b"ssh:"
+                ]))
+            )
+            .validate()
+        );
+        assert_eq!(
+            Err(KeyDataError::UnsupportedType("ssh-foo".to_owned())),
+            KeyData::from_blob(&blob(&[b"ssh-foo"]))
+        );
+        assert!(
+            match PublicKey::new(KeyType::SshRsa, "AAAA!".to_owned()).key_data() {
+                Err(KeyDataError::InvalidBase64(_)) => true,
+                _ => false,
+            }
+        );
+    }
+
+    #[test]
+    fn it_validates_the_declared_key_type() {
+        let rsa = BASE64.encode(&blob(&[b"ssh-rsa", &[1, 0, 1], &[0xc0]]));
+
+        assert_eq!(
+            Err(KeyDataError::TypeMismatch {
+                declared: "ssh-ed25519".to_owned(),
+                embedded: "ssh-rsa".to_owned(),
+            }),
+            PublicKey::new(KeyType::SshEd25519, rsa.clone()).validate()
+        );
+        assert_eq!(
+            PublicKey::new(KeyType::SshRsa, rsa.clone()).key_data(),
+            PublicKey::new(KeyType::SshRsa, rsa).validate()
+        );
+    }
+
+    #[test]
+    fn it_rejects_trailing_and_truncated_data() {
+        let mut data = blob(&[b"ssh-ed25519", &[9; 32]]);
+        data.extend_from_slice(b"xyz");
+        let subject = PublicKey::new(KeyType::SshEd25519, BASE64.encode(&data));
+
+        assert!(subject.key_data().is_ok());
+        assert_eq!(Err(KeyDataError::TrailingBytes(3)), subject.validate());
+
+        let truncated = blob(&[b"ssh-ed25519", &[9; 32]]);
+        let subject = PublicKey::new(
+            KeyType::SshEd25519,
+            BASE64.encode(&truncated[..truncated.len() - 1]),
+        );
+
+        assert_eq!(
+            Err(KeyDataError::Truncated("public key".to_owned())),
+            subject.validate()
+        );
+    }
+
+    #[test]
+    fn it_lists_keys_with_invalid_data() {
+        let input = format!(
+            "# keys\nssh-ed25519 {}\n{}\n",
+            BASE64.encode(&blob(&[b"ssh-rsa", &[3], &[5]])),
+            ED25519
+        );
+        let subject = input.parse::<KeysFile>().unwrap();
+
+        assert_eq!(
+            vec![(
+                2,
+                KeyDataError::TypeMismatch {
+                    declared: "ssh-ed25519".to_owned(),
+                    embedded: "ssh-rsa".to_owned(),
+                }
+            )],
+            subject.invalid_key_data()
+        );
+    }
+}
@@ -1,6 +1,31 @@
-use super::models::{KeyAuthorization, KeysFile, KeysFileLine, PublicKey};
+use super::models::{
+    Comment, ExpiryTime, ForwardPort, ForwardSpec, HostPattern, HostPatternList, KeyAuthorization,
+    KeysFile, KeysFileLine, PublicKey,
+};
+#[cfg(feature = "key_encoding")]
+use super::models::{Fingerprint, HashAlg};
+#[cfg(feature = "key_encoding")]
+use data_encoding::BASE64_NOPAD;
+use std::borrow::Cow;
 use std::fmt::{Display, Error, Formatter};
 
+/// Whether an option value can be written between double-quotes. A
+/// value ending in a backslash cannot, as `sshd` would read the closing
+/// quote as escaped.
+pub(crate) fn can_escape(value: &str) -> bool {
+    !value.ends_with('\\')
+}
+
+/// Escape an option value so it can be written between double-quotes,
+/// the reverse of how `sshd` reads it; see `can_escape`.
+pub(crate) fn escape(value: &str) -> Cow<str> {
+    if value.contains('"') {
+        Cow::Owned(value.replace('"', r#"\""#))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
 impl Display for PublicKey {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(f, "{} {}", self.key_type, self.encoded_key)
@@ -23,12 +48,139 @@ impl Display for KeyAuthorization {
     }
 }
 
+impl Display for HostPattern {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        if self.negated {
+            f.write_str("!")?;
+        }
+
+        f.write_str(&self.pattern)
+    }
+}
+
+impl Display for HostPatternList {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", pattern)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ForwardPort {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ForwardPort::Any => f.write_str("*"),
+            ForwardPort::Port(port) => write!(f, "{}", port),
+        }
+    }
+}
+
+impl Display for ForwardSpec {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) date.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months counted from March, so leap days fall at the end
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let year = year_of_era + era * 400;
+
+    if month < 10 {
+        (year, month + 3, day)
+    } else {
+        (year + 1, month - 9, day)
+    }
+}
+
+/// Times are written as briefly as possible: without seconds if they are
+/// zero, and without a time of day at midnight.
+impl Display for ExpiryTime {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let (year, month, day) = civil_from_days(self.unix_time / 86_400);
+        let seconds = self.unix_time % 86_400;
+
+        write!(f, "{:04}{:02}{:02}", year, month, day)?;
+        if seconds != 0 {
+            write!(f, "{:02}{:02}", seconds / 3600, seconds / 60 % 60)?;
+            if seconds % 60 != 0 {
+                write!(f, "{:02}", seconds % 60)?;
+            }
+        }
+        if self.utc {
+            f.write_str("Z")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "key_encoding")]
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}:", self.alg)?;
+
+        match self.alg {
+            HashAlg::Md5 => {
+                let bytes = self
+                    .digest
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<_>>();
+
+                write!(f, "{}", bytes.join(":"))
+            }
+            HashAlg::Sha256 => write!(f, "{}", BASE64_NOPAD.encode(&self.digest)),
+        }
+    }
+}
+
+impl Display for Comment {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}#{}", self.indent, self.text)
+    }
+}
+
+/// Keys which are unchanged since they were parsed, and invalid lines,
+/// are written exactly as they were read.
 impl Display for KeysFile {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        for line in &self.lines {
+        if self.byte_order_mark {
+            f.write_str("\u{feff}")?;
+        }
+
+        for (i, line) in self.lines.iter().enumerate() {
             match line {
-                KeysFileLine::Comment(val) => writeln!(f, "{}", val)?,
-                KeysFileLine::Key(val) => writeln!(f, "{}", val)?,
+                KeysFileLine::Blank(val) => write!(f, "{}", val)?,
+                KeysFileLine::Comment(val) => write!(f, "{}", val)?,
+                KeysFileLine::Key(val) => match val.original_text() {
+                    Some(text) => write!(f, "{}", text)?,
+                    None => write!(f, "{}", val)?,
+                },
+                KeysFileLine::Invalid { text, .. } => write!(f, "{}", text)?,
+            }
+
+            if self.final_newline || i + 1 < self.lines.len() {
+                let line_ending = self.line_endings.get(i).unwrap_or(&self.line_ending);
+
+                f.write_str(line_ending.as_str())?;
             }
         }
 
@@ -39,7 +191,7 @@ impl Display for KeysFile {
 #[cfg(test)]
 mod tests {
     use super::super::constants::KeyType;
-    use super::{KeyAuthorization, PublicKey};
+    use super::{Comment, KeyAuthorization, KeysFile, KeysFileLine, PublicKey};
 
     #[test]
     fn it_writes_a_key() {
@@ -89,7 +241,7 @@ mod tests {
             .push(("no-agent-forwarding".to_owned(), None));
         subject.options.push((
             "command".to_owned(),
-            Some("echo \\\"Hello, world!\\\"".to_owned()),
+            Some("echo \"Hello, world!\"".to_owned()),
         ));
         subject
             .options
@@ -106,4 +258,66 @@ mod tests {
 
         assert_eq!(&subject.to_string(), "no-agent-forwarding,command=\"echo \\\"Hello, world!\\\"\",environment=\"PATH=/bin:/sbin\",environment=\"LOGNAME=ssh-user\" ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM this is a more complex example");
     }
+
+    #[test]
+    fn it_preserves_the_formatting_of_unchanged_keys() {
+        let input = "# comment\n  \nno-pty,command=\"uptime\"   ssh-ed25519 foobar==\tcomment \nssh-ed25519  foobar==\n";
+        let mut subject = input.parse::<KeysFile>().unwrap();
+
+        assert_eq!(input, subject.to_string());
+
+        if let KeysFileLine::Key(ref mut key) = subject.lines[3] {
+            key.comments = "edited".to_owned();
+        }
+        subject.lines.push(KeysFileLine::Key(
+            KeyAuthorization::default().encoded_key("added".to_owned()),
+        ));
+
+        assert_eq!("# comment\n  \nno-pty,command=\"uptime\"   ssh-ed25519 foobar==\tcomment \nssh-ed25519 foobar== edited\nssh-rsa added\n", subject.to_string());
+    }
+
+    #[test]
+    fn it_writes_comments() {
+        let mut subject = KeysFile::default();
+        subject
+            .lines
+            .push(KeysFileLine::Comment(Comment::new(" admins".to_owned())));
+        subject.lines.push(KeysFileLine::Blank("".to_owned()));
+        subject.lines.push(KeysFileLine::Comment(Comment {
+            indent: "  ".to_owned(),
+            text: "indented".to_owned(),
+        }));
+
+        assert_eq!("# admins\n\n  #indented\n", subject.to_string());
+    }
+
+    #[test]
+    fn it_preserves_the_layout_of_a_file() {
+        let inputs = vec![
+            "ssh-ed25519 foobar==\r\n# comment\r\n",
+            "\u{feff}ssh-ed25519 foobar==\n",
+            "ssh-ed25519 foobar==\n\nssh-rsa foobar==",
+            "\u{feff}# comment\r\nssh-rsa foobar==",
+            "# comment\r\nssh-ed25519 foobar==\n\r\nssh-rsa foobar==\n",
+            "ssh-ed25519 foobar==\nno-pty ssh-ed25519 foobar==\r\n# comment",
+        ];
+
+        for input in inputs {
+            assert_eq!(input, input.parse::<KeysFile>().unwrap().to_string());
+            assert_eq!(input, KeysFile::parse_lenient(input).to_string());
+        }
+    }
+
+    #[test]
+    fn it_ends_new_lines_in_the_style_of_the_file() {
+        let mut subject = "# comment\r\nssh-ed25519 foobar==\n"
+            .parse::<KeysFile>()
+            .unwrap();
+        subject.lines.push(KeysFileLine::Blank("".to_owned()));
+
+        assert_eq!(
+            "# comment\r\nssh-ed25519 foobar==\n\r\n",
+            subject.to_string()
+        );
+    }
 }
@@ -0,0 +1,185 @@
+use super::models::{HashAlg, KeyDataError, PublicKey};
+
+/// Width of the randomart field, as in OpenSSH.
+const FIELD_WIDTH: usize = 17;
+/// Height of the randomart field, as in OpenSSH.
+const FIELD_HEIGHT: usize = 9;
+/// Symbols for how often the bishop visited a square, then the start and
+/// end squares.
+const SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+/// Walk the "drunken bishop" over the field from its centre, two bits
+/// of `digest` per step, counting how often each square is visited.
+/// The start and end squares are marked with the last two symbols.
+fn walk(digest: &[u8]) -> [[usize; FIELD_WIDTH]; FIELD_HEIGHT] {
+    let start = SYMBOLS.len() - 2;
+    let mut field = [[0; FIELD_WIDTH]; FIELD_HEIGHT];
+    let (mut x, mut y) = (FIELD_WIDTH / 2, FIELD_HEIGHT / 2);
+
+    for &byte in digest {
+        for step in 0..4_u8 {
+            let bits = byte >> (step * 2);
+
+            x = if bits & 0x1 == 0 {
+                x.saturating_sub(1)
+            } else {
+                (x + 1).min(FIELD_WIDTH - 1)
+            };
+            y = if bits & 0x2 == 0 {
+                y.saturating_sub(1)
+            } else {
+                (y + 1).min(FIELD_HEIGHT - 1)
+            };
+            if field[y][x] < start - 1 {
+                field[y][x] += 1;
+            }
+        }
+    }
+
+    field[FIELD_HEIGHT / 2][FIELD_WIDTH / 2] = start;
+    field[y][x] = start + 1;
+    field
+}
+
+/// A border line with `label` centred in it, as OpenSSH draws it.
+fn border(label: &str) -> String {
+    let before = (FIELD_WIDTH - label.len()) / 2;
+
+    format!(
+        "+{}{}{}+",
+        "-".repeat(before),
+        label,
+        "-".repeat(FIELD_WIDTH - before - label.len())
+    )
+}
+
+impl PublicKey {
+    /// The randomart ("visual host key") of the key, using the hash
+    /// algorithm `alg`, as `ssh-keygen -lv` draws it. Lines are separated
+    /// by `\n`, with none after the last.
+    ///
+    /// ```
+    /// use authorized_keys::openssh::v2::{HashAlg, KeyAuthorization};
+    ///
+    /// let authorization: KeyAuthorization = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM"
+    ///     .parse()
+    ///     .unwrap();
+    /// let art = authorization.key.randomart(HashAlg::Sha256).unwrap();
+    ///
+    /// assert_eq!(Some("+--[ED25519 256]--+"), art.lines().next());
+    /// assert_eq!(Some("+----[SHA256]-----+"), art.lines().last());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the key's data cannot be decoded.
+    pub fn randomart(&self, alg: HashAlg) -> Result<String, KeyDataError> {
+        let bits = self.key_data()?.bits();
+        let fingerprint = self.fingerprint(alg)?;
+
+        // OpenSSH drops the size if the title does not fit, and cuts off
+        // whatever still does not
+        let mut title = format!("[{} {}]", self.key_type.label(), bits);
+        if title.len() > FIELD_WIDTH {
+            title = format!("[{}]", self.key_type.label());
+        }
+        title.truncate(FIELD_WIDTH - 1);
+
+        let mut lines = vec![border(&title)];
+        for row in &walk(&fingerprint.digest) {
+            let symbols = row
+                .iter()
+                .map(|&visits| char::from(SYMBOLS[visits]))
+                .collect::<String>();
+
+            lines.push(format!("|{}|", symbols));
+        }
+        lines.push(border(&format!("[{}]", fingerprint.alg)));
+
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{parse_key, ED25519, RSA_1024, RSA_1024_CERT};
+
+    fn randomart(key: &str, alg: HashAlg) -> String {
+        parse_key(key).key.randomart(alg).unwrap()
+    }
+
+    #[test]
+    fn it_draws_randomart_as_ssh_keygen_does() {
+        assert_eq!(
+            "+--[ED25519 256]--+\n\
+             |o+*.+o     o   . |\n\
+             |.+=+o.  . = o E..|\n\
+             |oo =.  . + * o oo|\n\
+             |. . +...o + o   o|\n\
+             |   . oo So + ..o+|\n\
+             |       .  . o ooo|\n\
+             |            .o.  |\n\
+             |           .=.   |\n\
+             |            .+   |\n\
+             +----[SHA256]-----+",
+            randomart(ED25519, HashAlg::Sha256)
+        );
+        assert_eq!(
+            "+---[RSA 1024]----+\n\
+             | .    o          |\n\
+             |  o  + o         |\n\
+             | . o. =          |\n\
+             |o o o. o         |\n\
+             |o..+.o. S        |\n\
+             |.+ ooo.          |\n\
+             |o.+ .            |\n\
+             | ..o             |\n\
+             |  .E.            |\n\
+             +------[MD5]------+",
+            randomart(RSA_1024, HashAlg::Md5)
+        );
+    }
+
+    #[test]
+    fn it_draws_randomart_for_certificates() {
+        assert_eq!(
+            "+-[RSA-CERT 1024]-+\n\
+             |  . ..o          |\n\
+             | . = +.o   .     |\n\
+             |  o *.+ . o      |\n\
+             |   . +.... .     |\n\
+             |.   *+ +S . .    |\n\
+             |.. *oo= o. .     |\n\
+             |..+ **   o       |\n\
+             |.+ o.+=. ..      |\n\
+             |. ..E=oo=o       |\n\
+             +----[SHA256]-----+",
+            randomart(RSA_1024_CERT, HashAlg::Sha256)
+        );
+    }
+
+    #[test]
+    fn it_shortens_titles_which_do_not_fit() {
+        assert_eq!("+[ED25519-SK-CERT-+", border("[ED25519-SK-CERT"));
+        assert_eq!("+---[ECDSA 384]---+", border("[ECDSA 384]"));
+    }
+
+    #[test]
+    fn it_marks_where_the_bishop_starts_and_ends() {
+        // four steps up and to the left
+        let field = walk(&[0]);
+
+        assert_eq!(SYMBOLS.len() - 2, field[4][8]);
+        assert_eq!(SYMBOLS.len() - 1, field[0][4]);
+        assert_eq!(1, field[1][5]);
+        assert_eq!(
+            5,
+            field
+                .iter()
+                .flat_map(|row| row.iter())
+                .filter(|&&visits| visits != 0)
+                .count()
+        );
+    }
+}
@@ -1,26 +1,67 @@
-use super::models::{KeyAuthorization, KeyOption, KeyType, PublicKey};
+use super::constants::OptionName;
+use super::convert::is_env_name;
+use super::display::can_escape;
+use super::models::{
+    ExpiryTime, ForcedTunnel, KeyAuthorization, KeyOption, KeyOptions, KeyType, KeysFile,
+    KeysFileLine, OpensshVersion, OptionError, PublicKey, TypedKeyOption,
+};
+use super::parse::unescape;
 #[cfg(feature = "key_encoding")]
 use data_encoding::BASE64;
+use std::mem;
+use std::time::{Duration, SystemTime};
+
+/// The first release to understand `restrict`, and the flags which turn
+/// its restrictions back off.
+const RESTRICT_SINCE: OpensshVersion = OpensshVersion { major: 7, minor: 2 };
+
+/// The capability turned on or off by a flag which `restrict` also
+/// turns off.
+fn capability(name: &OptionName) -> Option<usize> {
+    match name {
+        OptionName::AgentForwarding | OptionName::NoAgentForwarding => Some(0),
+        OptionName::PortForwarding | OptionName::NoPortForwarding => Some(1),
+        OptionName::X11Forwarding | OptionName::NoX11Forwarding => Some(2),
+        OptionName::Pty | OptionName::NoPty => Some(3),
+        OptionName::UserRc | OptionName::NoUserRc => Some(4),
+        _ => None,
+    }
+}
 
-fn basic_escape(val: &str) -> String {
-    val.replace("\\", "\\\\").replace("\"", "\\\"")
+/// The security key requirement turned on or off by a flag, which
+/// `restrict` does not change.
+fn requirement(name: &OptionName) -> Option<usize> {
+    match name {
+        OptionName::TouchRequired | OptionName::NoTouchRequired => Some(0),
+        OptionName::VerifyRequired | OptionName::NoVerifyRequired => Some(1),
+        _ => None,
+    }
 }
 
 impl KeyAuthorization {
-    /// Adds a `KeyOption` to the key's options, without escaping the
-    /// value.
+    /// Adds a `KeyOption` (or `TypedKeyOption`) to the key's options,
+    /// whose value is escaped as it would be written in an
+    /// `authorized_keys` file (with `\"` for each double-quote). As with
+    /// `option`, the value is not checked.
     #[must_use]
-    pub fn raw_option(mut self, option: KeyOption) -> Self {
-        self.options.push(option);
+    pub fn raw_option<O: Into<KeyOption>>(self, option: O) -> Self {
+        let (name, value) = option.into();
 
-        self
+        self.option((name, value.map(|v| unescape(&v).into_owned())))
     }
 
-    /// Adds a `KeyOption` to the key's options, escaping any
-    /// double-quotes and slashes. Does not avoid double-escapes.
+    /// Adds a `KeyOption` (or `TypedKeyOption`) to the key's options.
+    /// The value is escaped when the key is written out.
+    ///
+    /// A value ending in a backslash cannot be written, as `sshd` would
+    /// read the closing quote as escaped. It is not rejected here, but
+    /// the key will not parse once written, and interpreting the option
+    /// (e.g. with `typed_options`) fails.
     #[must_use]
-    pub fn option(self, option: KeyOption) -> Self {
-        self.raw_option((option.0, option.1.map(|v| basic_escape(&v))))
+    pub fn option<O: Into<KeyOption>>(mut self, option: O) -> Self {
+        self.options.push(option.into());
+
+        self
     }
 
     /// Adds a `KeyOption` to the key's option, where the option is
@@ -38,30 +79,255 @@ impl KeyAuthorization {
         self
     }
 
-    /// Removes all options with the given option name.
+    /// Removes all options with the given option name, ignoring case.
     #[must_use]
     pub fn remove_named_options(mut self, name: &str) -> Self {
         self.options = self
             .options
             .into_iter()
-            .filter(|(n, _)| n != name)
+            .filter(|(n, _)| !n.eq_ignore_ascii_case(name))
             .collect::<Vec<_>>();
 
         self
     }
 
-    /// Remove all options with the given name and value.
+    /// Remove all options with the given name (ignoring case) and value.
     #[must_use]
     pub fn remove_options(mut self, option: &KeyOption) -> Self {
         self.options = self
             .options
             .into_iter()
-            .filter(|v| v.0 != option.0 || v.1 != option.1)
+            .filter(|v| !v.0.eq_ignore_ascii_case(&option.0) || v.1 != option.1)
             .collect::<Vec<_>>();
 
         self
     }
 
+    /// Rewrites the key's options in a canonical form, without changing
+    /// what `sshd` makes of them, so that equivalent keys compare equal.
+    ///
+    /// Names are lowercased. Flags which `restrict` does not affect come
+    /// first, sorted, keeping only the last given of `touch-required`
+    /// and `no-touch-required`, and of `verify-required` and
+    /// `no-verify-required`. Then comes `restrict`, if given, followed by
+    /// the last flag given after it for each capability it turns off,
+    /// sorted; `sshd` ignores the others. Options with values follow,
+    /// sorted by name but otherwise in order, without exact duplicates
+    /// of those which may be repeated. Unknown or invalid options are
+    /// kept last, in order.
+    #[must_use]
+    pub fn normalize(mut self) -> Self {
+        let mut flags: KeyOptions = Vec::new();
+        let mut restricted = false;
+        let mut capabilities: [Option<KeyOption>; 5] = Default::default();
+        let mut requirements: [Option<KeyOption>; 2] = Default::default();
+        let mut valued: KeyOptions = Vec::new();
+        let mut unknown: KeyOptions = Vec::new();
+
+        for (name, value) in self.options {
+            let option = (name.to_ascii_lowercase(), value);
+
+            match OptionName::parse_ignoring_case(&option.0) {
+                Some(ref name) if name.takes_value() != option.1.is_some() => unknown.push(option),
+                Some(OptionName::Restrict) => {
+                    restricted = true;
+                    capabilities = Default::default();
+                }
+                Some(ref name) if capability(name).is_some() => {
+                    if let Some(index) = capability(name) {
+                        capabilities[index] = Some(option);
+                    }
+                }
+                Some(ref name) if requirement(name).is_some() => {
+                    if let Some(index) = requirement(name) {
+                        requirements[index] = Some(option);
+                    }
+                }
+                Some(OptionName::CertAuthority) => {
+                    if !flags.contains(&option) {
+                        flags.push(option);
+                    }
+                }
+                // `sshd` rejects most of these when repeated, and uses the
+                // last `tunnel`, so keep every one
+                Some(OptionName::Command)
+                | Some(OptionName::From)
+                | Some(OptionName::Principals)
+                | Some(OptionName::Tunnel) => valued.push(option),
+                Some(_) => {
+                    if !valued.contains(&option) {
+                        valued.push(option);
+                    }
+                }
+                None => unknown.push(option),
+            }
+        }
+
+        let mut capabilities = capabilities
+            .iter()
+            .filter_map(Clone::clone)
+            .collect::<KeyOptions>();
+
+        flags.extend(requirements.iter().filter_map(Clone::clone));
+        flags.sort();
+        capabilities.sort();
+        valued.sort_by(|a, b| a.0.cmp(&b.0));
+
+        self.options = flags;
+        if restricted {
+            self.options.push((OptionName::Restrict.to_string(), None));
+        }
+        self.options.extend(capabilities);
+        self.options.extend(valued);
+        self.options.extend(unknown);
+
+        self
+    }
+
+    /// Rewrites the key's options as the shortest list which `sshd` in
+    /// the `target` release of OpenSSH (or later) makes the same of,
+    /// leaving out any options it ignores. For example, turning off
+    /// every capability is written as `restrict` from 7.2. Options are
+    /// written in the canonical form; see `normalize`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid
+    /// (including if any is unknown), in which case they cannot be
+    /// rewritten.
+    pub fn minimize(mut self, target: OpensshVersion) -> Result<Self, OptionError> {
+        let permissions = self.effective_permissions()?;
+        let capabilities = [
+            (
+                permissions.agent_forwarding,
+                TypedKeyOption::AgentForwarding,
+                TypedKeyOption::NoAgentForwarding,
+            ),
+            (
+                permissions.port_forwarding,
+                TypedKeyOption::PortForwarding,
+                TypedKeyOption::NoPortForwarding,
+            ),
+            (
+                permissions.x11_forwarding,
+                TypedKeyOption::X11Forwarding,
+                TypedKeyOption::NoX11Forwarding,
+            ),
+            (permissions.pty, TypedKeyOption::Pty, TypedKeyOption::NoPty),
+            (
+                permissions.user_rc,
+                TypedKeyOption::UserRc,
+                TypedKeyOption::NoUserRc,
+            ),
+        ];
+        let permitted = capabilities.iter().filter(|(on, ..)| *on).count();
+        let restrict = target >= RESTRICT_SINCE && permitted < capabilities.len() - permitted;
+        let mut options = Vec::new();
+
+        if permissions.cert_authority {
+            options.push(TypedKeyOption::CertAuthority);
+        }
+        if self.key.key_type.is_security_key() && !permissions.touch_required {
+            options.push(TypedKeyOption::NoTouchRequired);
+        }
+        if permissions.verify_required {
+            options.push(TypedKeyOption::VerifyRequired);
+        }
+        if restrict {
+            options.push(TypedKeyOption::Restrict);
+        }
+        for (on, enable, disable) in capabilities.iter().cloned() {
+            match (restrict, on) {
+                (true, true) => options.push(enable),
+                (false, false) => options.push(disable),
+                _ => {}
+            }
+        }
+        options.extend(permissions.forced_command.map(TypedKeyOption::Command));
+        options.extend(
+            permissions
+                .environment
+                .into_iter()
+                .map(|(name, value)| TypedKeyOption::Environment { name, value }),
+        );
+        options.extend(permissions.expiry_time.map(TypedKeyOption::ExpiryTime));
+        options.extend(permissions.from.map(TypedKeyOption::From));
+        if permissions.port_forwarding {
+            options.extend(
+                permissions
+                    .permit_listen
+                    .into_iter()
+                    .map(TypedKeyOption::PermitListen),
+            );
+            options.extend(
+                permissions
+                    .permit_open
+                    .into_iter()
+                    .map(TypedKeyOption::PermitOpen),
+            );
+        }
+        options.extend(permissions.principals.map(TypedKeyOption::Principals));
+        options.extend(permissions.forced_tunnel.map(|tunnel| match tunnel {
+            ForcedTunnel::Any => TypedKeyOption::Tunnel(None),
+            ForcedTunnel::Device(device) => TypedKeyOption::Tunnel(Some(device)),
+        }));
+
+        self.options = options.into_iter().map(KeyOption::from).collect();
+
+        Ok(self.normalize())
+    }
+
+    /// Sets the environment variable `name` to `value` when this key is
+    /// used, replacing any existing `environment` options for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if `name` is not a name `sshd` accepts,
+    /// made up of ASCII letters, digits and underscores, or if `value`
+    /// ends in a backslash, which cannot be written.
+    pub fn set_env(self, name: &str, value: &str) -> Result<Self, OptionError> {
+        if !is_env_name(name) || !can_escape(value) {
+            return Err(OptionError::InvalidValue {
+                name: OptionName::Environment.to_string(),
+                value: format!("{}={}", name, value),
+            });
+        }
+
+        Ok(self.remove_env(name).option(TypedKeyOption::Environment {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }))
+    }
+
+    /// Removes all `environment` options setting the variable `name`.
+    #[must_use]
+    pub fn remove_env(mut self, name: &str) -> Self {
+        self.options.retain(|(n, value)| {
+            !OptionName::Environment.matches(n)
+                || value
+                    .as_ref()
+                    .and_then(|value| value.find('=').map(|separator| &value[..separator]))
+                    != Some(name)
+        });
+
+        self
+    }
+
+    /// Sets the key to expire at `time`, replacing any existing
+    /// `expiry-time` options.
+    #[must_use]
+    pub fn expires_at(self, time: SystemTime) -> Self {
+        self.remove_named_options(&OptionName::ExpiryTime.to_string())
+            .option(TypedKeyOption::ExpiryTime(ExpiryTime::from(time)))
+    }
+
+    /// Sets the key to expire once `duration` has passed from now,
+    /// replacing any existing `expiry-time` options.
+    #[must_use]
+    pub fn expires_after(self, duration: Duration) -> Self {
+        self.expires_at(SystemTime::now() + duration)
+    }
+
     /// Removes the comments field for the key.
     #[must_use]
     pub fn remove_comments(mut self) -> Self {
@@ -102,6 +368,15 @@ impl KeyAuthorization {
         self
     }
 
+    /// Forgets the original formatting of the line this key was parsed
+    /// from, so it is always written in the canonical format.
+    #[must_use]
+    pub fn discard_formatting(mut self) -> Self {
+        self.source = None;
+
+        self
+    }
+
     /// Sets the public key data to the encoded form of the given bytes.
     #[cfg(feature = "key_encoding")]
     #[must_use]
@@ -112,6 +387,42 @@ impl KeyAuthorization {
     }
 }
 
+impl KeysFile {
+    /// Removes keys which have expired by `time`; see
+    /// `KeyAuthorization::is_expired_at`. Keys with invalid expiry times
+    /// are kept.
+    pub fn remove_expired_keys(&mut self, time: SystemTime) {
+        let lines = mem::replace(&mut self.lines, Vec::new());
+        let mut line_endings = mem::replace(&mut self.line_endings, Vec::new()).into_iter();
+
+        for line in lines {
+            let line_ending = line_endings.next();
+            let expired = match &line {
+                KeysFileLine::Key(key) => key.is_expired_at(time) == Ok(true),
+                _ => false,
+            };
+
+            if !expired {
+                self.lines.push(line);
+                self.line_endings.extend(line_ending);
+            }
+        }
+    }
+
+    /// Rewrites each key's options as the shortest equivalent list for
+    /// the `target` release of OpenSSH; see `KeyAuthorization::minimize`.
+    /// Keys with invalid options are left as they are.
+    pub fn minimize_options(&mut self, target: OpensshVersion) {
+        for line in &mut self.lines {
+            if let KeysFileLine::Key(key) = line {
+                if let Ok(minimized) = key.clone().minimize(target) {
+                    *key = minimized;
+                }
+            }
+        }
+    }
+}
+
 impl PublicKey {
     /// Sets the encoded key to the provided value.
     #[must_use]
@@ -142,7 +453,11 @@ impl PublicKey {
 
 #[cfg(test)]
 mod tests {
-    use super::{KeyAuthorization, KeyType, PublicKey};
+    use super::{
+        KeyAuthorization, KeyType, KeysFile, OpensshVersion, OptionError, PublicKey, SystemTime,
+        TypedKeyOption,
+    };
+    use crate::testing::parse_key;
 
     #[test]
     fn it_adds_options() {
@@ -163,6 +478,19 @@ mod tests {
         )
     }
 
+    #[test]
+    fn it_adds_typed_options() {
+        let subject = KeyAuthorization::default()
+            .option(TypedKeyOption::Restrict)
+            .option(TypedKeyOption::Command(r#"echo "hi""#.to_owned()))
+            .raw_option(TypedKeyOption::Tunnel(Some(0)));
+
+        assert_eq!(
+            &subject.to_string(),
+            r#"restrict,command="echo \"hi\"",tunnel="0" ssh-rsa "#
+        )
+    }
+
     #[test]
     fn it_removes_options() {
         let mut subject = KeyAuthorization::default();
@@ -207,6 +535,16 @@ mod tests {
         assert_eq!("", subject.remove_comments().comments);
     }
 
+    #[test]
+    fn it_discards_formatting() {
+        let subject = "ssh-ed25519   foobar=="
+            .parse::<KeyAuthorization>()
+            .unwrap();
+
+        assert!(subject.original_text().is_some());
+        assert_eq!(None, subject.discard_formatting().original_text());
+    }
+
     #[test]
     fn it_sets_nested_key_parameters() {
         let subject = KeyAuthorization::default()
@@ -261,4 +599,240 @@ mod tests {
             PublicKey::default().data_from_bytes(data).encoded_key
         );
     }
+
+    #[test]
+    fn it_sets_expiry() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let subject = KeyAuthorization::default()
+            .option(("expiry-time".to_owned(), Some("20200101".to_owned())))
+            .option_name("restrict".to_owned())
+            .expires_at(UNIX_EPOCH + Duration::from_secs(1_893_501_000));
+
+        assert_eq!(
+            &subject.to_string(),
+            r#"restrict,expiry-time="203001011230Z" ssh-rsa "#
+        );
+
+        let subject = KeyAuthorization::default().expires_after(Duration::from_secs(3600));
+        let later = SystemTime::now() + Duration::from_secs(7200);
+
+        assert_eq!(Ok(false), subject.is_expired_at(SystemTime::now()));
+        assert_eq!(Ok(true), subject.is_expired_at(later));
+    }
+
+    #[test]
+    fn it_removes_expired_keys() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let mut subject = "# break-glass\nexpiry-time=\"20200101Z\" ssh-ed25519 foobar==\r\nexpiry-time=\"20300101Z\" ssh-ed25519 foobar==\r\nssh-rsa foobar==\n"
+            .parse::<KeysFile>()
+            .unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(1, subject.expired_keys(now).len());
+        subject.remove_expired_keys(now);
+        assert_eq!(
+            "# break-glass\nexpiry-time=\"20300101Z\" ssh-ed25519 foobar==\r\nssh-rsa foobar==\n",
+            subject.to_string()
+        );
+    }
+
+    #[test]
+    fn it_sets_environment_variables() {
+        let subject =
+            r#"environment="A=1",restrict,environment="B=2",environment="A=3" ssh-rsa foobar=="#
+                .parse::<KeyAuthorization>()
+                .unwrap()
+                .set_env("A", "4")
+                .unwrap()
+                .set_env("C", "")
+                .unwrap();
+
+        assert_eq!(
+            Ok(vec![
+                ("B".to_owned(), "2".to_owned()),
+                ("A".to_owned(), "4".to_owned()),
+                ("C".to_owned(), "".to_owned()),
+            ]),
+            subject.env_vars()
+        );
+        assert_eq!(
+            r#"restrict,environment="B=2" ssh-rsa foobar=="#,
+            subject
+                .remove_env("A")
+                .remove_env("C")
+                .remove_env("missing")
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_environment_names() {
+        assert_eq!(
+            Err(OptionError::InvalidValue {
+                name: "environment".to_owned(),
+                value: "LC-ALL=C".to_owned(),
+            }),
+            KeyAuthorization::default().set_env("LC-ALL", "C")
+        );
+        assert!(KeyAuthorization::default().set_env("", "x").is_err());
+    }
+
+    #[test]
+    fn it_rejects_values_which_cannot_be_written() {
+        assert_eq!(
+            Err(OptionError::InvalidValue {
+                name: "environment".to_owned(),
+                value: "DIR=C:\\".to_owned(),
+            }),
+            KeyAuthorization::default().set_env("DIR", "C:\\")
+        );
+
+        let subject =
+            KeyAuthorization::default().option(("command".to_owned(), Some("dir C:\\".to_owned())));
+
+        assert!(subject.typed_options().is_err());
+    }
+
+    #[test]
+    fn it_keeps_parsed_values_when_editing() {
+        let parsed =
+            r#"command="echo \"hi\" C:\dir" ssh-rsa foobar=="#.parse::<KeyAuthorization>().unwrap();
+        let subject = parsed
+            .clone()
+            .clear_options()
+            .option(parsed.options[0].clone())
+            .option_name("restrict".to_owned());
+
+        assert_eq!(
+            r#"command="echo \"hi\" C:\dir",restrict"#,
+            subject.options_string()
+        );
+        assert_eq!(
+            subject.options,
+            subject
+                .to_string()
+                .parse::<KeyAuthorization>()
+                .unwrap()
+                .options
+        );
+    }
+
+    #[test]
+    fn it_removes_options_ignoring_case() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let subject =
+            r#"No-Pty,Environment="A=1",EXPIRY-TIME="20200101",restrict ssh-rsa foobar=="#
+                .parse::<KeyAuthorization>()
+                .unwrap();
+
+        assert_eq!(
+            "restrict",
+            subject
+                .clone()
+                .remove_named_options("no-pty")
+                .remove_options(&("environment".to_owned(), Some("A=1".to_owned())))
+                .remove_named_options("expiry-time")
+                .options_string()
+        );
+        assert_eq!(
+            "No-Pty,EXPIRY-TIME=\"20200101\",restrict",
+            subject.clone().remove_env("A").options_string()
+        );
+        assert_eq!(
+            r#"No-Pty,Environment="A=1",restrict,expiry-time="203001011230Z""#,
+            subject
+                .expires_at(UNIX_EPOCH + Duration::from_secs(1_893_501_000))
+                .options_string()
+        );
+    }
+
+    #[test]
+    fn it_normalizes_options() {
+        let normalize = |options: &str| {
+            parse_key(&format!("{} ssh-rsa foobar==", options))
+                .normalize()
+                .options_string()
+        };
+
+        assert_eq!(
+            r#"cert-authority,no-pty,command="uptime",permitopen="a:1""#,
+            normalize(r#"permitopen="a:1",No-Pty,command="uptime",cert-authority,no-pty,permitopen="a:1""#)
+        );
+        assert_eq!(
+            "restrict,pty,x11-forwarding",
+            normalize("no-agent-forwarding,restrict,restrict,X11-forwarding,no-pty,pty")
+        );
+        assert_eq!(
+            r#"environment="B=1",environment="A=2",from="a",from="a",x-vendor"#,
+            normalize(r#"x-vendor,from="a",environment="B=1",from="a",environment="A=2",environment="B=1""#)
+        );
+        assert_eq!("pty", normalize("no-pty,pty"));
+        assert_eq!(
+            "cert-authority,no-verify-required,touch-required,restrict",
+            normalize("restrict,no-touch-required,verify-required,cert-authority,Touch-Required,no-verify-required")
+        );
+        assert_eq!(
+            normalize("restrict,pty,agent-forwarding"),
+            normalize("Agent-Forwarding,restrict,agent-forwarding,PTY")
+        );
+    }
+
+    #[test]
+    fn it_minimizes_options() {
+        let minimize = |options: &str, major: u32, minor: u32| {
+            parse_key(&format!("{} ssh-ed25519 foobar==", options))
+                .minimize(OpensshVersion::new(major, minor))
+                .unwrap()
+                .options_string()
+        };
+        let legacy = "no-port-forwarding,no-X11-forwarding,no-agent-forwarding,no-pty,no-user-rc";
+
+        assert_eq!("restrict", minimize(legacy, 7, 2));
+        assert_eq!(
+            "no-agent-forwarding,no-port-forwarding,no-pty,no-user-rc,no-x11-forwarding",
+            minimize(legacy, 7, 1)
+        );
+        assert_eq!("restrict,pty", minimize("no-pty,restrict,pty", 8, 0));
+        assert_eq!(
+            "no-pty",
+            minimize(
+                "restrict,agent-forwarding,port-forwarding,X11-forwarding,user-rc",
+                8,
+                0
+            )
+        );
+        assert_eq!(
+            r#"tunnel="any""#,
+            minimize("restrict,agent-forwarding,port-forwarding,X11-forwarding,user-rc,pty,no-touch-required,tunnel=\"any\"", 8, 0)
+        );
+        assert_eq!(
+            r#"tunnel="1""#,
+            minimize(r#"tunnel="any",tunnel="1""#, 8, 0)
+        );
+        assert_eq!(
+            r#"no-port-forwarding,command="uptime",environment="A=1""#,
+            minimize(r#"permitopen="a:1",environment="A=1",no-port-forwarding,command="uptime",environment="A=2""#, 8, 0)
+        );
+        assert_eq!(
+            r#"cert-authority,restrict,expiry-time="20200101",principals="alice""#,
+            minimize(r#"cert-authority,restrict,principals="alice",expiry-time="20300101",expiry-time="20200101""#, 8, 0)
+        );
+    }
+
+    #[test]
+    fn it_minimizes_the_options_of_each_key() {
+        let mut subject = "no-pty,no-pty ssh-ed25519 foobar==\ncommand=\"a\",command=\"b\" ssh-ed25519 foobar==\nx-vendor,no-pty,no-pty ssh-ed25519 foobar==\n"
+            .parse::<KeysFile>()
+            .unwrap();
+
+        subject.minimize_options(OpensshVersion::new(8, 0));
+
+        assert_eq!(
+            "no-pty ssh-ed25519 foobar==\ncommand=\"a\",command=\"b\" ssh-ed25519 foobar==\nx-vendor,no-pty,no-pty ssh-ed25519 foobar==\n",
+            subject.to_string()
+        );
+    }
 }
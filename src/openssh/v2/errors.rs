@@ -0,0 +1,397 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+/// The part of an `authorized_keys` line the parser was expecting to
+/// find when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// An option name, such as `no-pty` or `command`
+    OptionName,
+    /// A double-quote, opening an option value
+    OpeningQuote,
+    /// A double-quote, closing an option value
+    ClosingQuote,
+    /// Whitespace separating the parts of a line
+    Whitespace,
+    /// A key type, such as `ssh-ed25519`
+    KeyType,
+    /// A base64-encoded public key
+    Base64,
+    /// The end of the line, after the comments
+    EndOfLine,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        f.write_str(match self {
+            Expected::OptionName => "an option name",
+            Expected::OpeningQuote => "an opening quote",
+            Expected::ClosingQuote => "a closing quote",
+            Expected::Whitespace => "whitespace",
+            Expected::KeyType => "a key type",
+            Expected::Base64 => "a base64-encoded key",
+            Expected::EndOfLine => "the end of the line",
+        })
+    }
+}
+
+/// An error encountered while parsing an `authorized_keys` file or line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The line does not match the `authorized_keys` format.
+    Syntax {
+        /// Line number of the error, starting from 1
+        line: usize,
+        /// Column of the offending token, in characters, starting from 1
+        column: usize,
+        /// The text found where the expected token should have been
+        /// (empty at the end of the line)
+        token: String,
+        /// What the parser was expecting to find
+        expected: Expected,
+    },
+    /// The input could not be read.
+    Io {
+        /// Line number being read, starting from 1
+        line: usize,
+        /// Kind of I/O error
+        kind: io::ErrorKind,
+        /// Description of the I/O error
+        message: String,
+    },
+}
+
+impl ParseError {
+    /// Line number of the error, starting from 1.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        match self {
+            ParseError::Syntax { line, .. } | ParseError::Io { line, .. } => *line,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ParseError::Syntax {
+                line,
+                column,
+                token,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "line {}, column {}: expected {}, ",
+                    line, column, expected
+                )?;
+
+                if token.is_empty() {
+                    f.write_str("found end of line")
+                } else {
+                    write!(f, "found `{}`", token)
+                }
+            }
+            ParseError::Io { line, message, .. } => {
+                write!(f, "line {}: failed to read: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// An error encountered while interpreting a `KeyOption`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    /// The option takes no value, but was given one.
+    ValueNotAllowed {
+        /// Name of the option
+        name: String,
+        /// Value it was given
+        value: String,
+    },
+    /// The option takes a value, but was not given one.
+    MissingValue {
+        /// Name of the option
+        name: String,
+    },
+    /// The value given is not valid for the option.
+    InvalidValue {
+        /// Name of the option
+        name: String,
+        /// Value it was given
+        value: String,
+    },
+    /// The option may only be given once, but was repeated.
+    Repeated {
+        /// Name of the option
+        name: String,
+    },
+    /// The option is only valid alongside another, which was not given.
+    RequiresOption {
+        /// Name of the option
+        name: String,
+        /// Name of the option it requires
+        required: String,
+    },
+    /// The option is not one `sshd` knows, so it would refuse the key.
+    Unknown {
+        /// Name of the option
+        name: String,
+    },
+}
+
+impl Display for OptionError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            OptionError::ValueNotAllowed { name, value } => write!(
+                f,
+                "option `{}` takes no value, but was given \"{}\"",
+                name, value
+            ),
+            OptionError::MissingValue { name } => write!(f, "option `{}` requires a value", name),
+            OptionError::InvalidValue { name, value } => {
+                write!(f, "invalid value for option `{}`: \"{}\"", name, value)
+            }
+            OptionError::Repeated { name } => write!(f, "option `{}` may only be given once", name),
+            OptionError::RequiresOption { name, required } => write!(
+                f,
+                "option `{}` is only valid with option `{}`",
+                name, required
+            ),
+            OptionError::Unknown { name } => write!(f, "unknown option `{}`", name),
+        }
+    }
+}
+
+impl Error for OptionError {}
+
+/// An error in a `from` pattern list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// An entry in the list is empty, or only "!".
+    EmptyEntry,
+    /// A CIDR block's prefix is too long for its address, or the address
+    /// has bits set beyond the prefix (e.g. `10.1.2.3/8`).
+    InconsistentMask(String),
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            PatternError::EmptyEntry => f.write_str("empty entry in pattern list"),
+            PatternError::InconsistentMask(pattern) => {
+                write!(f, "inconsistent mask length for network `{}`", pattern)
+            }
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+/// An error in the value of a `permitopen` or `permitlisten` option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardSpecError {
+    /// The host is missing, too long, or has an unclosed "[".
+    InvalidHost(String),
+    /// The port is missing, or is neither `*` nor a number from 1 to
+    /// 65535.
+    InvalidPort(String),
+}
+
+impl Display for ForwardSpecError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ForwardSpecError::InvalidHost(spec) => {
+                write!(f, "invalid permission hostname in `{}`", spec)
+            }
+            ForwardSpecError::InvalidPort(spec) => {
+                write!(f, "invalid permission port in `{}`", spec)
+            }
+        }
+    }
+}
+
+impl Error for ForwardSpecError {}
+
+/// An error in the value of an `expiry-time` option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeError {
+    /// The time is not written as `YYYYMMDD[HHMM[SS]][Z]`.
+    InvalidFormat(String),
+    /// A field of the time is out of range, or the time is before 1970.
+    OutOfRange(String),
+}
+
+impl Display for TimeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            TimeError::InvalidFormat(time) => {
+                write!(f, "`{}` is not written as YYYYMMDD[HHMM[SS]][Z]", time)
+            }
+            TimeError::OutOfRange(time) => write!(f, "`{}` is not a valid time", time),
+        }
+    }
+}
+
+impl Error for TimeError {}
+
+/// An error decoding the binary data of a public key.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyDataError {
+    /// The key data is not valid base64.
+    InvalidBase64(data_encoding::DecodeError),
+    /// The data ends part way through the named field.
+    Truncated(String),
+    /// The named field has an invalid value, such as text which is not
+    /// UTF-8, or an Ed25519 key which is not 32 bytes long.
+    InvalidField(String),
+    /// The data is for a type of key which cannot be decoded.
+    UnsupportedType(String),
+    /// The data is for a different type of key than the line declares.
+    TypeMismatch {
+        /// Key type written on the line
+        declared: String,
+        /// Key type named by the data
+        embedded: String,
+    },
+    /// The data continues for this many bytes after the key.
+    TrailingBytes(usize),
+}
+
+#[cfg(feature = "key_encoding")]
+impl Display for KeyDataError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            KeyDataError::InvalidBase64(err) => write!(f, "key data is not valid base64: {}", err),
+            KeyDataError::Truncated(field) => write!(f, "key data ends within the {}", field),
+            KeyDataError::InvalidField(field) => write!(f, "key data has an invalid {}", field),
+            KeyDataError::UnsupportedType(key_type) => {
+                write!(f, "key type `{}` cannot be decoded", key_type)
+            }
+            KeyDataError::TypeMismatch { declared, embedded } => write!(
+                f,
+                "key is declared as `{}`, but its data is for `{}`",
+                declared, embedded
+            ),
+            KeyDataError::TrailingBytes(count) => {
+                write!(f, "key data has {} unexpected bytes at the end", count)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "key_encoding")]
+impl Error for KeyDataError {}
+
+/// An error in a written key fingerprint.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FingerprintError {
+    /// The fingerprint does not start with a known hash algorithm (e.g.
+    /// `SHA256:`).
+    UnknownAlgorithm(String),
+    /// The digest is not written correctly for its algorithm, or is the
+    /// wrong length.
+    InvalidDigest(String),
+}
+
+#[cfg(feature = "key_encoding")]
+impl Display for FingerprintError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            FingerprintError::UnknownAlgorithm(fingerprint) => write!(
+                f,
+                "`{}` does not start with a known hash algorithm",
+                fingerprint
+            ),
+            FingerprintError::InvalidDigest(fingerprint) => {
+                write!(f, "`{}` is not a valid fingerprint", fingerprint)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "key_encoding")]
+impl Error for FingerprintError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expected, OptionError, ParseError};
+
+    #[test]
+    fn it_describes_syntax_errors() {
+        let subject = ParseError::Syntax {
+            line: 3,
+            column: 9,
+            token: "ssh-foo".to_owned(),
+            expected: Expected::KeyType,
+        };
+
+        assert_eq!(
+            "line 3, column 9: expected a key type, found `ssh-foo`",
+            subject.to_string()
+        );
+    }
+
+    #[test]
+    fn it_describes_errors_at_the_end_of_a_line() {
+        let subject = ParseError::Syntax {
+            line: 1,
+            column: 22,
+            token: "".to_owned(),
+            expected: Expected::ClosingQuote,
+        };
+
+        assert_eq!(
+            "line 1, column 22: expected a closing quote, found end of line",
+            subject.to_string()
+        );
+    }
+
+    #[test]
+    fn it_describes_option_errors() {
+        let subject = OptionError::ValueNotAllowed {
+            name: "no-pty".to_owned(),
+            value: "x".to_owned(),
+        };
+
+        assert_eq!(
+            "option `no-pty` takes no value, but was given \"x\"",
+            subject.to_string()
+        );
+
+        let subject = OptionError::RequiresOption {
+            name: "principals".to_owned(),
+            required: "cert-authority".to_owned(),
+        };
+
+        assert_eq!(
+            "option `principals` is only valid with option `cert-authority`",
+            subject.to_string()
+        );
+
+        let subject = OptionError::Unknown {
+            name: "comand".to_owned(),
+        };
+
+        assert_eq!("unknown option `comand`", subject.to_string());
+    }
+
+    #[cfg(feature = "key_encoding")]
+    #[test]
+    fn it_describes_key_data_errors() {
+        let subject = super::KeyDataError::TypeMismatch {
+            declared: "ssh-ed25519".to_owned(),
+            embedded: "ssh-rsa".to_owned(),
+        };
+
+        assert_eq!(
+            "key is declared as `ssh-ed25519`, but its data is for `ssh-rsa`",
+            subject.to_string()
+        );
+    }
+}
@@ -0,0 +1,867 @@
+use super::constants::OptionName;
+use super::errors::OptionError;
+use super::models::{
+    EffectivePermissions, ExpiryTime, ForcedTunnel, ForwardPort, ForwardSpec, HostPattern,
+    HostPatternList, IpNetwork, KeyAuthorization, TypedKeyOption,
+};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::SystemTime;
+
+/// Match `s` against a pattern of `*` and `?` wildcards, as `sshd`'s
+/// `match_pattern` does.
+fn wildcard_match(s: &[u8], pattern: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'*', _)) => {
+            let rest = &pattern[pattern.iter().take_while(|&&c| c == b'*').count()..];
+
+            rest.is_empty() || (0..s.len()).any(|start| wildcard_match(&s[start..], rest))
+        }
+        Some((&c, rest)) => match s.split_first() {
+            Some((&sc, s_rest)) if c == b'?' || c == sc => wildcard_match(s_rest, rest),
+            _ => false,
+        },
+    }
+}
+
+/// Check each entry of a pattern list, as `sshd` does: `None` if a
+/// negated entry matched, otherwise whether any entry matched.
+fn match_list<F: Fn(&HostPattern) -> bool>(
+    patterns: &HostPatternList,
+    is_match: F,
+) -> Option<bool> {
+    let mut matched = false;
+
+    for pattern in &patterns.patterns {
+        if is_match(pattern) {
+            if pattern.negated {
+                return None;
+            }
+            matched = true;
+        }
+    }
+
+    Some(matched)
+}
+
+impl IpNetwork {
+    /// `address`, with any bits beyond the network prefix cleared.
+    pub(crate) fn masked(&self, address: IpAddr) -> IpAddr {
+        match address {
+            IpAddr::V4(v4) => {
+                let mask = u32::max_value()
+                    .checked_shl(32 - u32::from(self.prefix_len.min(32)))
+                    .unwrap_or(0);
+
+                IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask))
+            }
+            IpAddr::V6(v6) => {
+                let mask = u128::max_value()
+                    .checked_shl(128 - u32::from(self.prefix_len.min(128)))
+                    .unwrap_or(0);
+
+                IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+            }
+        }
+    }
+
+    /// Whether `address` is within this network.
+    #[must_use]
+    pub fn contains(&self, address: IpAddr) -> bool {
+        address.is_ipv4() == self.address.is_ipv4() && self.masked(address) == self.address
+    }
+}
+
+impl HostPatternList {
+    /// Whether a client connecting from `address`, whose name resolved
+    /// to `hostname`, matches the list, following `sshd`'s
+    /// `match_host_and_ip`.
+    ///
+    /// The address is matched against each entry, using CIDR matching
+    /// for address and network entries and wildcard matching otherwise;
+    /// the host name is matched case-insensitively against each entry as
+    /// a wildcard. If either matches a negated entry the client is
+    /// rejected, otherwise it is accepted if either matched any entry.
+    /// With no host name the address is used in its place, as `sshd`
+    /// does when `UseDNS` is off.
+    #[must_use]
+    pub fn matches(&self, address: IpAddr, hostname: Option<&str>) -> bool {
+        let address_text = address.to_string();
+        let hostname = hostname.map_or_else(|| address_text.clone(), str::to_ascii_lowercase);
+
+        let address_match = match_list(self, |pattern| match pattern.network {
+            Some(network) => network.contains(address),
+            None => wildcard_match(address_text.as_bytes(), pattern.pattern.as_bytes()),
+        });
+        let hostname_match = match_list(self, |pattern| {
+            wildcard_match(
+                hostname.as_bytes(),
+                pattern.pattern.to_ascii_lowercase().as_bytes(),
+            )
+        });
+
+        match (address_match, hostname_match) {
+            (Some(address_match), Some(hostname_match)) => address_match || hostname_match,
+            _ => false,
+        }
+    }
+}
+
+impl ForwardPort {
+    fn allows(self, port: u16) -> bool {
+        match self {
+            ForwardPort::Any => true,
+            ForwardPort::Port(permitted) => permitted == port,
+        }
+    }
+}
+
+impl ForwardSpec {
+    /// Whether this `permitopen` permission allows connecting to `host`
+    /// on `port`. The host must be exactly as permitted, unless any host
+    /// is permitted.
+    #[must_use]
+    pub fn allows_open(&self, host: &str, port: u16) -> bool {
+        self.port.allows(port) && (self.host == "*" || self.host == host)
+    }
+
+    /// Whether this `permitlisten` permission allows listening on `host`
+    /// and `port`. The host is matched case-insensitively against the
+    /// permitted host, which may contain wildcards.
+    #[must_use]
+    pub fn allows_listen(&self, host: &str, port: u16) -> bool {
+        self.port.allows(port)
+            && wildcard_match(host.to_ascii_lowercase().as_bytes(), self.host.as_bytes())
+    }
+}
+
+/// Set an option which may only be given once.
+fn once<T>(slot: &mut Option<T>, value: T, name: &OptionName) -> Result<(), OptionError> {
+    if slot.is_some() {
+        return Err(OptionError::Repeated {
+            name: name.to_string(),
+        });
+    }
+    *slot = Some(value);
+
+    Ok(())
+}
+
+impl KeyAuthorization {
+    /// The time after which `sshd` will no longer accept this key: the
+    /// earliest of its `expiry-time` options, if it has any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if an `expiry-time` option is invalid.
+    pub fn expiry_time(&self) -> Result<Option<ExpiryTime>, OptionError> {
+        let mut earliest: Option<ExpiryTime> = None;
+
+        for option in self
+            .options
+            .iter()
+            .filter(|(name, _)| OptionName::ExpiryTime.matches(name))
+        {
+            if let TypedKeyOption::ExpiryTime(time) = TypedKeyOption::try_from(option.clone())? {
+                if earliest.map_or(true, |earliest| time.unix_time < earliest.unix_time) {
+                    earliest = Some(time);
+                }
+            }
+        }
+
+        Ok(earliest)
+    }
+
+    /// Whether this key has expired by `time`, so `sshd` would no longer
+    /// accept it. Keys without an `expiry-time` never expire.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if an `expiry-time` option is invalid.
+    pub fn is_expired_at(&self, time: SystemTime) -> Result<bool, OptionError> {
+        Ok(self.expiry_time()?.map_or(false, |expiry| {
+            expiry.unix_time < ExpiryTime::from(time).unix_time
+        }))
+    }
+
+    /// What this key actually permits, once `sshd` has applied all of
+    /// its options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid (for
+    /// which `sshd` would reject the key), including if an option is
+    /// unknown, if `command`,
+    /// `from` or `principals` is given more than once, or `principals`
+    /// is given without `cert-authority`.
+    pub fn effective_permissions(&self) -> Result<EffectivePermissions, OptionError> {
+        let security_key = self.key.key_type.is_security_key();
+        let mut permissions = EffectivePermissions {
+            agent_forwarding: true,
+            port_forwarding: true,
+            x11_forwarding: true,
+            pty: true,
+            user_rc: true,
+            forced_command: None,
+            forced_tunnel: None,
+            environment: Vec::new(),
+            from: None,
+            permit_open: Vec::new(),
+            permit_listen: Vec::new(),
+            expiry_time: None,
+            touch_required: security_key,
+            verify_required: false,
+            cert_authority: false,
+            principals: None,
+        };
+
+        for option in self.typed_options()? {
+            match option {
+                TypedKeyOption::Restrict => {
+                    permissions.agent_forwarding = false;
+                    permissions.port_forwarding = false;
+                    permissions.x11_forwarding = false;
+                    permissions.pty = false;
+                    permissions.user_rc = false;
+                }
+                TypedKeyOption::AgentForwarding => permissions.agent_forwarding = true,
+                TypedKeyOption::NoAgentForwarding => permissions.agent_forwarding = false,
+                TypedKeyOption::PortForwarding => permissions.port_forwarding = true,
+                TypedKeyOption::NoPortForwarding => permissions.port_forwarding = false,
+                TypedKeyOption::X11Forwarding => permissions.x11_forwarding = true,
+                TypedKeyOption::NoX11Forwarding => permissions.x11_forwarding = false,
+                TypedKeyOption::Pty => permissions.pty = true,
+                TypedKeyOption::NoPty => permissions.pty = false,
+                TypedKeyOption::UserRc => permissions.user_rc = true,
+                TypedKeyOption::NoUserRc => permissions.user_rc = false,
+                TypedKeyOption::TouchRequired => permissions.touch_required = security_key,
+                TypedKeyOption::NoTouchRequired => permissions.touch_required = false,
+                TypedKeyOption::VerifyRequired => permissions.verify_required = security_key,
+                TypedKeyOption::NoVerifyRequired => permissions.verify_required = false,
+                TypedKeyOption::Command(command) => once(
+                    &mut permissions.forced_command,
+                    command,
+                    &OptionName::Command,
+                )?,
+                TypedKeyOption::From(patterns) => {
+                    once(&mut permissions.from, patterns, &OptionName::From)?
+                }
+                TypedKeyOption::Environment { name, value } => {
+                    // the first value given for a variable is used
+                    if permissions.environment.iter().all(|(set, _)| *set != name) {
+                        permissions.environment.push((name, value));
+                    }
+                }
+                TypedKeyOption::PermitOpen(spec) => permissions.permit_open.push(spec),
+                TypedKeyOption::PermitListen(spec) => permissions.permit_listen.push(spec),
+                TypedKeyOption::ExpiryTime(time) => {
+                    if permissions
+                        .expiry_time
+                        .map_or(true, |earliest| time.unix_time < earliest.unix_time)
+                    {
+                        permissions.expiry_time = Some(time);
+                    }
+                }
+                TypedKeyOption::CertAuthority => permissions.cert_authority = true,
+                TypedKeyOption::Principals(principals) => once(
+                    &mut permissions.principals,
+                    principals,
+                    &OptionName::Principals,
+                )?,
+                // the last device given is used
+                TypedKeyOption::Tunnel(device) => {
+                    permissions.forced_tunnel =
+                        Some(device.map_or(ForcedTunnel::Any, ForcedTunnel::Device))
+                }
+                TypedKeyOption::Unknown(name, _) => return Err(OptionError::Unknown { name }),
+            }
+        }
+
+        if permissions.principals.is_some() && !permissions.cert_authority {
+            return Err(OptionError::RequiresOption {
+                name: OptionName::Principals.to_string(),
+                required: OptionName::CertAuthority.to_string(),
+            });
+        }
+
+        Ok(permissions)
+    }
+
+    /// Names of environment variables set more than once by this key's
+    /// options, in the order they are first set. `sshd` ignores all but
+    /// the first value for each, so the others are likely a mistake.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid.
+    pub fn repeated_env_vars(&self) -> Result<Vec<String>, OptionError> {
+        let vars = self.env_vars()?;
+        let mut repeated: Vec<String> = Vec::new();
+
+        for (index, (name, _)) in vars.iter().enumerate() {
+            if vars[..index].iter().any(|(earlier, _)| earlier == name) && !repeated.contains(name)
+            {
+                repeated.push(name.clone());
+            }
+        }
+
+        Ok(repeated)
+    }
+
+    /// Whether `sshd` would allow a local forward (`ssh -L`) to `host` on
+    /// `port` with this key. Port forwarding must be permitted, and if
+    /// the key has any `permitopen` options the forward must be allowed
+    /// by one of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid.
+    pub fn allows_local_forward(&self, host: &str, port: u16) -> Result<bool, OptionError> {
+        let permissions = self.effective_permissions()?;
+
+        Ok(permissions.port_forwarding
+            && (permissions.permit_open.is_empty()
+                || permissions
+                    .permit_open
+                    .iter()
+                    .any(|spec| spec.allows_open(host, port))))
+    }
+
+    /// Whether `sshd` would allow a remote forward (`ssh -R`) listening
+    /// on `host` and `port` with this key. Port forwarding must be
+    /// permitted, and if the key has any `permitlisten` options the
+    /// forward must be allowed by one of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid.
+    pub fn allows_remote_forward(&self, host: &str, port: u16) -> Result<bool, OptionError> {
+        let permissions = self.effective_permissions()?;
+
+        Ok(permissions.port_forwarding
+            && (permissions.permit_listen.is_empty()
+                || permissions
+                    .permit_listen
+                    .iter()
+                    .any(|spec| spec.allows_listen(host, port))))
+    }
+
+    /// Whether `sshd` would accept this key from a client connecting from
+    /// `address`, whose name resolved to `hostname`, according to its
+    /// `from` option; see `HostPatternList::matches`. Keys without a
+    /// `from` option are accepted from anywhere.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid (for
+    /// which `sshd` would reject the key), including if `from` is given
+    /// more than once.
+    pub fn accepts_from(
+        &self,
+        address: IpAddr,
+        hostname: Option<&str>,
+    ) -> Result<bool, OptionError> {
+        Ok(self
+            .effective_permissions()?
+            .from
+            .map_or(true, |patterns| patterns.matches(address, hostname)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::models::KeyType;
+    use super::*;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn it_matches_wildcards() {
+        let cases = vec![
+            ("host.example.com", "*.example.com", true),
+            ("example.com", "*.example.com", false),
+            ("host1", "host?", true),
+            ("host12", "host?", false),
+            ("anything", "*", true),
+            ("", "*", true),
+            ("abc", "a**c", true),
+            ("abc", "a*?", true),
+            ("a", "a*?", false),
+            ("abc", "abc", true),
+            ("abc", "ab", false),
+        ];
+
+        for (s, pattern, expected) in cases {
+            assert_eq!(
+                expected,
+                wildcard_match(s.as_bytes(), pattern.as_bytes()),
+                "{:?} against {:?}",
+                s,
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn it_checks_networks() {
+        let network = IpNetwork {
+            address: ip("10.0.0.0"),
+            prefix_len: 8,
+        };
+
+        assert!(network.contains(ip("10.255.0.1")));
+        assert!(!network.contains(ip("11.0.0.1")));
+        assert!(!network.contains(ip("::a00:1")));
+        assert!(IpNetwork {
+            address: ip("::"),
+            prefix_len: 0,
+        }
+        .contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn it_matches_hosts_and_addresses() {
+        let cases = vec![
+            ("10.0.0.0/8", "10.2.3.4", None, true),
+            ("10.0.0.0/8", "192.0.2.1", None, false),
+            ("10.0.0.0/8,!10.2.3.4", "10.2.3.4", None, false),
+            ("!10.2.3.4,10.0.0.0/8", "10.2.3.4", None, false),
+            ("10.2.3.*", "10.2.3.4", None, true),
+            ("*.example.com", "192.0.2.1", Some("Host.Example.COM"), true),
+            ("*.EXAMPLE.com", "192.0.2.1", Some("host.example.com"), true),
+            ("*.example.com", "192.0.2.1", None, false),
+            (
+                "*,!bad.example.com",
+                "192.0.2.1",
+                Some("bad.example.com"),
+                false,
+            ),
+            (
+                "*,!192.0.2.0/24",
+                "192.0.2.1",
+                Some("good.example.com"),
+                false,
+            ),
+            ("2001:db8::/32", "2001:db8::1", None, true),
+            ("2001:db8::/32", "2001:db9::1", None, false),
+            ("10.0.0.0/8", "::ffff:10.0.0.1", None, false),
+            ("10.0.0.0/8", "10.0.0.1", Some("10.0.0.0/8"), true),
+        ];
+
+        for (patterns, address, hostname, expected) in cases {
+            let subject = patterns.parse::<HostPatternList>().unwrap();
+
+            assert_eq!(
+                expected,
+                subject.matches(ip(address), hostname),
+                "{:?} from {:?} ({:?})",
+                patterns,
+                address,
+                hostname
+            );
+        }
+    }
+
+    #[test]
+    fn it_checks_where_keys_are_accepted_from() {
+        let anywhere = KeyAuthorization::default();
+        let restricted = KeyAuthorization::default().option((
+            "from".to_owned(),
+            Some("10.0.0.0/8,*.example.com".to_owned()),
+        ));
+
+        assert_eq!(Ok(true), anywhere.accepts_from(ip("192.0.2.1"), None));
+        assert_eq!(Ok(true), restricted.accepts_from(ip("10.2.3.4"), None));
+        assert_eq!(
+            Ok(true),
+            restricted.accepts_from(ip("192.0.2.1"), Some("host.example.com"))
+        );
+        assert_eq!(
+            Ok(false),
+            restricted.accepts_from(ip("192.0.2.1"), Some("host.example.org"))
+        );
+        assert_eq!(
+            Err(OptionError::Repeated {
+                name: "from".to_owned(),
+            }),
+            restricted
+                .clone()
+                .option(("from".to_owned(), Some("*".to_owned())))
+                .accepts_from(ip("10.2.3.4"), None)
+        );
+        assert_eq!(
+            Err(OptionError::InvalidValue {
+                name: "from".to_owned(),
+                value: "10.1.2.3/8".to_owned(),
+            }),
+            KeyAuthorization::default()
+                .option(("from".to_owned(), Some("10.1.2.3/8".to_owned())))
+                .accepts_from(ip("10.2.3.4"), None)
+        );
+    }
+
+    fn key_with(options: &[(&str, Option<&str>)]) -> KeyAuthorization {
+        options
+            .iter()
+            .fold(KeyAuthorization::default(), |key, (name, value)| {
+                key.option(((*name).to_owned(), value.map(str::to_owned)))
+            })
+    }
+
+    #[test]
+    fn it_checks_local_forwards() {
+        let cases = vec![
+            (vec![], "example.com", 443, true),
+            (vec![("restrict", None)], "example.com", 443, false),
+            (
+                vec![("no-port-forwarding", None)],
+                "example.com",
+                443,
+                false,
+            ),
+            (
+                vec![("restrict", None), ("port-forwarding", None)],
+                "example.com",
+                443,
+                true,
+            ),
+            (
+                vec![("port-forwarding", None), ("restrict", None)],
+                "example.com",
+                443,
+                false,
+            ),
+            (
+                vec![("permitopen", Some("example.com:443"))],
+                "example.com",
+                443,
+                true,
+            ),
+            (
+                vec![("permitopen", Some("example.com:443"))],
+                "example.com",
+                80,
+                false,
+            ),
+            (
+                vec![("permitopen", Some("example.com:443"))],
+                "Example.com",
+                443,
+                false,
+            ),
+            (
+                vec![
+                    ("permitopen", Some("example.com:443")),
+                    ("permitopen", Some("*:22")),
+                ],
+                "db.internal",
+                22,
+                true,
+            ),
+            (
+                vec![("permitopen", Some("[2001:db8::1]:*"))],
+                "2001:db8::1",
+                8080,
+                true,
+            ),
+            (
+                vec![("permitopen", Some("*.example.com:443"))],
+                "www.example.com",
+                443,
+                false,
+            ),
+            (
+                vec![("permitlisten", Some("8080"))],
+                "example.com",
+                443,
+                true,
+            ),
+        ];
+
+        for (options, host, port, expected) in cases {
+            assert_eq!(
+                Ok(expected),
+                key_with(&options).allows_local_forward(host, port),
+                "{:?} to {}:{}",
+                options,
+                host,
+                port
+            );
+        }
+    }
+
+    #[test]
+    fn it_checks_remote_forwards() {
+        let cases = vec![
+            (vec![], "localhost", 8080, true),
+            (vec![("restrict", None)], "localhost", 8080, false),
+            (
+                vec![("permitlisten", Some("8080"))],
+                "localhost",
+                8080,
+                true,
+            ),
+            (
+                vec![("permitlisten", Some("8080"))],
+                "localhost",
+                8081,
+                false,
+            ),
+            (
+                vec![("permitlisten", Some("localhost:*"))],
+                "LocalHost",
+                9000,
+                true,
+            ),
+            (
+                vec![("permitlisten", Some("127.0.0.?:8080"))],
+                "127.0.0.1",
+                8080,
+                true,
+            ),
+            (
+                vec![("permitlisten", Some("localhost:8080"))],
+                "0.0.0.0",
+                8080,
+                false,
+            ),
+            (
+                vec![("permitopen", Some("example.com:443"))],
+                "localhost",
+                8080,
+                true,
+            ),
+        ];
+
+        for (options, host, port, expected) in cases {
+            assert_eq!(
+                Ok(expected),
+                key_with(&options).allows_remote_forward(host, port),
+                "{:?} on {}:{}",
+                options,
+                host,
+                port
+            );
+        }
+    }
+
+    #[test]
+    fn it_checks_expiry() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let at = |unix_time| UNIX_EPOCH + Duration::from_secs(unix_time);
+        let subject = key_with(&[
+            ("expiry-time", Some("20300101Z")),
+            ("expiry-time", Some("20291231")),
+        ]);
+
+        assert_eq!(
+            Ok(Some(ExpiryTime {
+                unix_time: 1_893_369_600,
+                utc: false,
+            })),
+            subject.expiry_time()
+        );
+        assert_eq!(Ok(false), subject.is_expired_at(at(1_893_369_600)));
+        assert_eq!(Ok(true), subject.is_expired_at(at(1_893_369_601)));
+        assert_eq!(
+            Ok(false),
+            KeyAuthorization::default().is_expired_at(at(u64::from(u32::max_value())))
+        );
+        assert_eq!(
+            Err(OptionError::InvalidValue {
+                name: "expiry-time".to_owned(),
+                value: "soon".to_owned(),
+            }),
+            key_with(&[("expiry-time", Some("soon"))]).is_expired_at(at(0))
+        );
+    }
+
+    #[test]
+    fn it_computes_effective_permissions() {
+        let subject = key_with(&[
+            ("restrict", None),
+            ("pty", None),
+            ("command", Some("uptime")),
+            ("environment", Some("A=1")),
+            ("environment", Some("B=2")),
+            ("environment", Some("A=3")),
+            ("permitopen", Some("example.com:443")),
+            ("expiry-time", Some("20300101")),
+            ("verify-required", None),
+        ])
+        .effective_permissions()
+        .unwrap();
+
+        assert!(!subject.agent_forwarding);
+        assert!(!subject.port_forwarding);
+        assert!(!subject.x11_forwarding);
+        assert!(subject.pty);
+        assert!(!subject.user_rc);
+        assert_eq!(Some("uptime".to_owned()), subject.forced_command);
+        assert_eq!(
+            vec![
+                ("A".to_owned(), "1".to_owned()),
+                ("B".to_owned(), "2".to_owned()),
+            ],
+            subject.environment
+        );
+        assert_eq!(1, subject.permit_open.len());
+        assert_eq!(None, subject.from);
+        assert_eq!(
+            Some(1_893_456_000),
+            subject.expiry_time.map(|t| t.unix_time)
+        );
+        assert!(!subject.touch_required);
+        assert!(!subject.verify_required);
+    }
+
+    #[test]
+    fn it_applies_options_in_order() {
+        let subject = key_with(&[
+            ("no-pty", None),
+            ("agent-forwarding", None),
+            ("restrict", None),
+            ("X11-forwarding", None),
+        ])
+        .effective_permissions()
+        .unwrap();
+
+        assert!(!subject.agent_forwarding);
+        assert!(subject.x11_forwarding);
+        assert!(!subject.pty);
+
+        let subject = key_with(&[("no-user-rc", None)])
+            .effective_permissions()
+            .unwrap();
+
+        assert!(subject.agent_forwarding && subject.port_forwarding && subject.pty);
+        assert!(!subject.user_rc);
+    }
+
+    #[test]
+    fn it_tells_any_tunnel_apart_from_none() {
+        let forced_tunnel = |options: &[(&str, Option<&str>)]| {
+            key_with(options)
+                .effective_permissions()
+                .unwrap()
+                .forced_tunnel
+        };
+
+        assert_eq!(None, forced_tunnel(&[]));
+        assert_eq!(
+            Some(ForcedTunnel::Any),
+            forced_tunnel(&[("tunnel", Some("any"))])
+        );
+        assert_eq!(
+            Some(ForcedTunnel::Device(2)),
+            forced_tunnel(&[("tunnel", Some("any")), ("tunnel", Some("2"))])
+        );
+        assert_eq!(
+            Some(ForcedTunnel::Any),
+            forced_tunnel(&[("tunnel", Some("2")), ("tunnel", Some("any"))])
+        );
+    }
+
+    #[test]
+    fn it_considers_the_key_type_for_touch_and_verify() {
+        let security_key = key_with(&[("verify-required", None)])
+            .key_type(KeyType::SkSshEd25519)
+            .effective_permissions()
+            .unwrap();
+
+        assert!(security_key.touch_required);
+        assert!(security_key.verify_required);
+
+        let untouched = key_with(&[("no-touch-required", None)])
+            .key_type(KeyType::SkEcdsaSha2Nistp256)
+            .effective_permissions()
+            .unwrap();
+
+        assert!(!untouched.touch_required);
+
+        // the last of each pair is used
+        let touched = key_with(&[
+            ("no-touch-required", None),
+            ("touch-required", None),
+            ("verify-required", None),
+            ("no-verify-required", None),
+        ])
+        .key_type(KeyType::SkSshEd25519)
+        .effective_permissions()
+        .unwrap();
+
+        assert!(touched.touch_required);
+        assert!(!touched.verify_required);
+    }
+
+    #[test]
+    fn it_rejects_repeated_options() {
+        assert_eq!(
+            Err(OptionError::Repeated {
+                name: "command".to_owned(),
+            }),
+            key_with(&[("command", Some("a")), ("command", Some("b"))]).effective_permissions()
+        );
+    }
+
+    #[test]
+    fn it_finds_repeated_environment_variables() {
+        let subject = key_with(&[
+            ("environment", Some("A=1")),
+            ("environment", Some("B=2")),
+            ("environment", Some("A=3")),
+            ("environment", Some("A=4")),
+        ]);
+
+        assert_eq!(Ok(vec!["A".to_owned()]), subject.repeated_env_vars());
+        assert_eq!(
+            Ok(Vec::new()),
+            key_with(&[("environment", Some("A=1"))]).repeated_env_vars()
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_options() {
+        assert_eq!(
+            Err(OptionError::Unknown {
+                name: "comand".to_owned(),
+            }),
+            key_with(&[("comand", Some("rm"))]).effective_permissions()
+        );
+    }
+
+    #[test]
+    fn it_requires_cert_authority_for_principals() {
+        let subject = key_with(&[("cert-authority", None), ("principals", Some("alice,bob"))])
+            .effective_permissions()
+            .unwrap();
+
+        assert!(subject.cert_authority);
+        assert_eq!(
+            Some(vec!["alice".to_owned(), "bob".to_owned()]),
+            subject.principals
+        );
+        assert_eq!(
+            Err(OptionError::RequiresOption {
+                name: "principals".to_owned(),
+                required: "cert-authority".to_owned(),
+            }),
+            key_with(&[("principals", Some("alice"))]).effective_permissions()
+        );
+        assert_eq!(
+            Err(OptionError::Repeated {
+                name: "principals".to_owned(),
+            }),
+            key_with(&[
+                ("cert-authority", None),
+                ("principals", Some("alice")),
+                ("principals", Some("bob")),
+            ])
+            .effective_permissions()
+        );
+    }
+}
@@ -1,6 +1,18 @@
-use super::models::{KeyAuthorization, PublicKey};
+use super::constants::OptionName;
+use super::display::escape;
+#[cfg(feature = "key_encoding")]
+use super::models::KeyData;
+use super::models::{
+    ExpiryTime, KeyAuthorization, KeyAuthorizationRef, KeyOptions, KeyOptionsIter, KeyOptionsRef,
+    KeyType, KeysFile, KeysFileLine, LineEnding, OptionError, PublicKey, PublicKeyRef, SourceLine,
+    TypedKeyOption,
+};
 #[cfg(feature = "key_encoding")]
 use data_encoding::BASE64;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 impl KeyAuthorization {
     #[must_use]
@@ -9,12 +21,252 @@ impl KeyAuthorization {
         self.options
             .iter()
             .map(|(name, val)| match val {
-                Some(v) => format!("{}=\"{}\"", name, v),
+                Some(v) => format!("{}=\"{}\"", name, escape(v)),
                 None => name.to_owned(),
             })
             .collect::<Vec<_>>()
             .join(",")
     }
+
+    /// Key options, interpreted as `TypedKeyOption`s.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first `OptionError` if any option known to `sshd` has
+    /// a missing or invalid value.
+    pub fn typed_options(&self) -> Result<Vec<TypedKeyOption>, OptionError> {
+        self.options
+            .iter()
+            .cloned()
+            .map(TypedKeyOption::try_from)
+            .collect()
+    }
+
+    /// Environment variables set by the key's `environment` options, as
+    /// (name, value) pairs in the order they are given. `sshd` only uses
+    /// the first value given for each variable; see
+    /// `KeyAuthorization::repeated_env_vars`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if any option known to `sshd` has a
+    /// missing or invalid value, including an invalid variable name.
+    pub fn env_vars(&self) -> Result<Vec<(String, String)>, OptionError> {
+        Ok(self
+            .typed_options()?
+            .into_iter()
+            .filter_map(|option| match option {
+                TypedKeyOption::Environment { name, value } => Some((name, value)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    #[must_use]
+    /// Whether this key is trusted as a certificate authority (with the
+    /// `cert-authority` option), rather than for logging in with
+    /// directly.
+    pub fn is_cert_authority(&self) -> bool {
+        self.options
+            .iter()
+            .any(|(name, _)| OptionName::CertAuthority.matches(name))
+    }
+
+    /// Principals listed by the key's `principals` option, one of which
+    /// a certificate signed by this authority must name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OptionError` if the key's options are invalid,
+    /// including if `principals` is repeated or given without
+    /// `cert-authority`.
+    pub fn principals(&self) -> Result<Option<Vec<String>>, OptionError> {
+        self.effective_permissions()
+            .map(|permissions| permissions.principals)
+    }
+
+    #[must_use]
+    /// The line this key was parsed from, if it was parsed from text.
+    pub fn source(&self) -> Option<&SourceLine> {
+        self.source.as_ref()
+    }
+
+    #[must_use]
+    /// The original text of the line this key was parsed from, provided
+    /// the key has not been changed since.
+    pub fn original_text(&self) -> Option<&str> {
+        self.source
+            .as_ref()
+            .filter(|source| source.describes(self))
+            .map(SourceLine::text)
+    }
+}
+
+impl<'a> KeyAuthorizationRef<'a> {
+    #[must_use]
+    /// Copy into an owned `KeyAuthorization`.
+    pub fn to_authorization(&self) -> KeyAuthorization {
+        KeyAuthorization {
+            options: self.options.to_key_options(),
+            key: self.key.to_public_key(),
+            comments: self.comments.to_owned(),
+            source: None,
+        }
+    }
+}
+
+impl<'a> KeyOptionsRef<'a> {
+    #[must_use]
+    /// Text of the options, as written.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    #[must_use]
+    /// Whether there are no options.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    #[must_use]
+    /// Iterate over the options, in the order they were written.
+    pub fn iter(&self) -> KeyOptionsIter<'a> {
+        KeyOptionsIter { rest: self.text }
+    }
+
+    #[must_use]
+    /// Copy into owned `KeyOptions`.
+    pub fn to_key_options(&self) -> KeyOptions {
+        self.iter()
+            .map(|(name, val)| (name.to_owned(), val.map(Cow::into_owned)))
+            .collect()
+    }
+}
+
+impl<'a> PublicKeyRef<'a> {
+    #[must_use]
+    /// Copy into an owned `PublicKey`.
+    pub fn to_public_key(&self) -> PublicKey {
+        PublicKey {
+            key_type: KeyType::from(self.key_type),
+            encoded_key: self.encoded_key.to_owned(),
+        }
+    }
+}
+
+impl KeysFile {
+    #[must_use]
+    /// Keys which have expired by `time`; see
+    /// `KeyAuthorization::is_expired_at`. Keys with invalid expiry times
+    /// are not included.
+    pub fn expired_keys(&self, time: SystemTime) -> Vec<&KeyAuthorization> {
+        self.keys()
+            .filter(|key| key.is_expired_at(time) == Ok(true))
+            .collect()
+    }
+
+    #[must_use]
+    /// Keys trusted as certificate authorities; see
+    /// `KeyAuthorization::is_cert_authority`.
+    pub fn cert_authorities(&self) -> Vec<&KeyAuthorization> {
+        self.keys().filter(|key| key.is_cert_authority()).collect()
+    }
+
+    #[must_use]
+    /// Keys trusted for logging in with directly, rather than as
+    /// certificate authorities.
+    pub fn plain_keys(&self) -> Vec<&KeyAuthorization> {
+        self.keys().filter(|key| !key.is_cert_authority()).collect()
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &KeyAuthorization> {
+        self.lines.iter().filter_map(|line| match line {
+            KeysFileLine::Key(key) => Some(key),
+            _ => None,
+        })
+    }
+}
+
+impl ExpiryTime {
+    #[must_use]
+    /// The time as a `SystemTime`, reading it as UTC.
+    pub fn system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.unix_time)
+    }
+}
+
+impl LineEnding {
+    #[must_use]
+    /// The characters which end a line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+impl SourceLine {
+    #[must_use]
+    /// Original text of the line, without any line ending.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[must_use]
+    /// Span of the key options (empty if there were none).
+    pub fn options_span(&self) -> Range<usize> {
+        self.options.clone()
+    }
+
+    #[must_use]
+    /// Span of the key type.
+    pub fn key_type_span(&self) -> Range<usize> {
+        self.key_type.clone()
+    }
+
+    #[must_use]
+    /// Span of the base64-encoded key.
+    pub fn encoded_key_span(&self) -> Range<usize> {
+        self.encoded_key.clone()
+    }
+
+    #[must_use]
+    /// Span of the comments (empty if there were none).
+    pub fn comments_span(&self) -> Range<usize> {
+        self.comments.clone()
+    }
+
+    /// Whether each part of the line still matches the given key.
+    fn describes(&self, key: &KeyAuthorization) -> bool {
+        self.text[self.options_span()] == key.options_string()
+            && self.text[self.key_type_span()] == key.key.key_type.to_string()
+            && self.text[self.encoded_key_span()] == key.key.encoded_key
+            && self.text[self.comments_span()] == key.comments
+    }
+}
+
+#[cfg(feature = "key_encoding")]
+impl KeyData {
+    #[must_use]
+    /// Size of the key in bits, as `ssh-keygen -l` reports it: the size
+    /// of the modulus for RSA and DSA keys, and of the curve for others.
+    /// For a certificate, the size of the key it certifies.
+    pub fn bits(&self) -> usize {
+        match self {
+            KeyData::Dsa { p: integer, .. }
+            | KeyData::Rsa {
+                modulus: integer, ..
+            } => integer.first().map_or(0, |first| {
+                integer.len() * 8 - first.leading_zeros() as usize
+            }),
+            KeyData::Ecdsa { curve, .. } | KeyData::SkEcdsa { curve, .. } => {
+                curve.trim_start_matches("nistp").parse().unwrap_or(0)
+            }
+            KeyData::Ed25519 { .. } | KeyData::SkEd25519 { .. } => 256,
+            KeyData::Certificate(certificate) => certificate.key.bits(),
+        }
+    }
 }
 
 impl PublicKey {
@@ -31,7 +283,7 @@ impl PublicKey {
 
 #[cfg(test)]
 mod tests {
-    use super::KeyAuthorization;
+    use super::{KeyAuthorization, KeysFile, SourceLine};
 
     #[test]
     fn it_generates_an_option_string() {
@@ -49,6 +301,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_separates_cert_authorities_from_plain_keys() {
+        let subject = "# internal CA\nCert-Authority,principals=\"alice,bob\" ssh-ed25519 foobar== ca\nno-pty ssh-ed25519 foobar== laptop\n"
+            .parse::<KeysFile>()
+            .unwrap();
+        let authorities = subject.cert_authorities();
+        let plain = subject.plain_keys();
+
+        assert_eq!(1, authorities.len());
+        assert_eq!("ca", authorities[0].comments);
+        assert_eq!(
+            Ok(Some(vec!["alice".to_owned(), "bob".to_owned()])),
+            authorities[0].principals()
+        );
+        assert_eq!(1, plain.len());
+        assert_eq!("laptop", plain[0].comments);
+        assert_eq!(Ok(None), plain[0].principals());
+    }
+
+    #[test]
+    fn it_gets_the_original_text_of_unchanged_keys() {
+        let line = "no-pty   ssh-ed25519 foobar==  comments ";
+        let mut subject = line.parse::<KeyAuthorization>().unwrap();
+
+        assert_eq!(Some(line), subject.original_text());
+        assert_eq!(Some(line), subject.source().map(SourceLine::text));
+
+        subject.comments = "changed".to_owned();
+
+        assert_eq!(None, subject.original_text());
+        assert_eq!(Some(line), subject.source().map(SourceLine::text));
+        assert_eq!(None, KeyAuthorization::default().original_text());
+    }
+
     #[cfg(feature = "key_encoding")]
     #[test]
     fn it_gets_key_bytes() {
@@ -63,4 +349,23 @@ mod tests {
             subject.key.data_bytes().expect("decoding should succeed")
         );
     }
+
+    #[cfg(feature = "key_encoding")]
+    #[test]
+    fn it_gets_key_sizes() {
+        use super::KeyData;
+
+        let rsa = KeyData::Rsa {
+            exponent: vec![1, 0, 1],
+            modulus: vec![0x7f; 256],
+        };
+        let ecdsa = KeyData::Ecdsa {
+            curve: "nistp521".to_owned(),
+            point: vec![4],
+        };
+
+        assert_eq!(2047, rsa.bits());
+        assert_eq!(521, ecdsa.bits());
+        assert_eq!(256, KeyData::Ed25519 { key: [0; 32] }.bits());
+    }
 }
@@ -0,0 +1,135 @@
+use super::models::{
+    Fingerprint, HashAlg, KeyAuthorization, KeyDataError, KeysFile, KeysFileLine, PublicKey,
+};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+impl PublicKey {
+    /// Fingerprint of the key's data, using the hash algorithm `alg`, as
+    /// `ssh-keygen -l` shows it. As there, a certificate has the
+    /// fingerprint of the key it certifies.
+    ///
+    /// ```
+    /// use authorized_keys::openssh::v2::{HashAlg, KeyAuthorization};
+    ///
+    /// let authorization: KeyAuthorization = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM"
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     "SHA256:khAC3AmpVGUK3vjBz9U9Yd5Zzy8rPjkTCHkZ/vTZXTA",
+    ///     authorization.key.fingerprint(HashAlg::Sha256).unwrap().to_string()
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the key is not valid base64, or is a
+    /// certificate whose key cannot be decoded.
+    pub fn fingerprint(&self, alg: HashAlg) -> Result<Fingerprint, KeyDataError> {
+        let blob = self.plain_blob()?;
+        let digest = match &alg {
+            HashAlg::Md5 => Md5::digest(&blob).to_vec(),
+            HashAlg::Sha256 => Sha256::digest(&blob).to_vec(),
+        };
+
+        Ok(Fingerprint { alg, digest })
+    }
+}
+
+impl KeyAuthorization {
+    /// A line describing the key as `ssh-keygen -lf` prints it, with its
+    /// size, fingerprint, comments and type.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyDataError` if the key's data cannot be decoded.
+    pub fn fingerprint_line(&self, alg: HashAlg) -> Result<String, KeyDataError> {
+        let bits = self.key.key_data()?.bits();
+        let comments = self.comments.trim();
+
+        Ok(format!(
+            "{} {} {} ({})",
+            bits,
+            self.key.fingerprint(alg)?,
+            if comments.is_empty() {
+                "no comment"
+            } else {
+                comments
+            },
+            self.key.key_type.label()
+        ))
+    }
+}
+
+impl KeysFile {
+    /// Keys whose fingerprint, using the same hash algorithm, is
+    /// `fingerprint`. Keys which are not valid base64 are skipped.
+    #[must_use]
+    pub fn keys_with_fingerprint(&self, fingerprint: &Fingerprint) -> Vec<&KeyAuthorization> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                KeysFileLine::Key(key)
+                    if key.key.fingerprint(fingerprint.alg.clone()).as_ref() == Ok(fingerprint) =>
+                {
+                    Some(key)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{parse_key, ED25519, RSA_1024_CERT};
+
+    #[test]
+    fn it_computes_fingerprints_as_ssh_keygen_does() {
+        let subject = parse_key(ED25519);
+
+        assert_eq!(
+            "SHA256:khAC3AmpVGUK3vjBz9U9Yd5Zzy8rPjkTCHkZ/vTZXTA",
+            subject
+                .key
+                .fingerprint(HashAlg::Sha256)
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "MD5:2b:84:41:f7:47:2c:0c:69:08:2c:df:66:eb:3d:41:50",
+            subject.key.fingerprint(HashAlg::Md5).unwrap().to_string()
+        );
+        assert_eq!(
+            "256 SHA256:khAC3AmpVGUK3vjBz9U9Yd5Zzy8rPjkTCHkZ/vTZXTA no comment (ED25519)",
+            subject.fingerprint_line(HashAlg::Sha256).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_fingerprints_certificates_by_their_key() {
+        let subject = parse_key(&format!("{} alice", RSA_1024_CERT));
+
+        assert_eq!(
+            "1024 SHA256:d5ACPhbSrsflD6gb4WQWySy0vTsi4doP1nuEv96bK6w alice (RSA-CERT)",
+            subject.fingerprint_line(HashAlg::Sha256).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_finds_keys_by_fingerprint() {
+        let subject = format!("# admin\n{} admin\nssh-rsa foobar==\n", ED25519)
+            .parse::<KeysFile>()
+            .unwrap();
+        let fingerprint = "SHA256:khAC3AmpVGUK3vjBz9U9Yd5Zzy8rPjkTCHkZ/vTZXTA"
+            .parse::<Fingerprint>()
+            .unwrap();
+
+        let found = subject.keys_with_fingerprint(&fingerprint);
+
+        assert_eq!(1, found.len());
+        assert_eq!("admin", found[0].comments);
+    }
+}
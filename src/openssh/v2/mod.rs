@@ -1,10 +1,22 @@
 //! Formats and functions for OpenSSH v2 `authorized_keys` files
 
+#[cfg(feature = "key_encoding")]
+mod assess;
 mod constants;
+mod convert;
+#[cfg(feature = "key_encoding")]
+mod decode;
 mod display;
+#[cfg(feature = "key_encoding")]
+mod draw;
 mod edit;
+mod errors;
+mod evaluate;
 mod get;
+#[cfg(feature = "key_encoding")]
+mod hash;
 mod models;
 mod parse;
+mod read;
 
 pub use models::*;
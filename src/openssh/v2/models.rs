@@ -1,23 +1,275 @@
+#[cfg(feature = "key_encoding")]
 #[doc(inline)]
-pub use super::constants::KeyType;
+pub use super::constants::HashAlg;
+#[doc(inline)]
+pub use super::constants::{KeyType, OptionName};
+#[doc(inline)]
+pub use super::errors::{
+    Expected, ForwardSpecError, OptionError, ParseError, PatternError, TimeError,
+};
+#[cfg(feature = "key_encoding")]
+#[doc(inline)]
+pub use super::errors::{FingerprintError, KeyDataError};
+#[doc(inline)]
+pub use super::read::KeysFileReader;
+use std::borrow::Cow;
 use std::iter::FromIterator;
+use std::net::IpAddr;
+use std::ops::Range;
 
 /// A key or key/value token which set parameters on the way the
 /// given public key is used by OpenSSH.
 ///
-/// Inner slashes or double-quotes must be escaped.
+/// Values are kept unescaped, and only escaped when written out.
 ///
 /// ```
 /// use authorized_keys::openssh::v2::KeyOption;
 ///
 /// let simple_option: KeyOption = ("no-agent-forwarding".to_owned(), None);
-/// let value_option: KeyOption = ("command".to_owned(), Some("echo \\\"Hello, world!\\\"".to_owned()));
+/// let value_option: KeyOption = ("command".to_owned(), Some("echo \"Hello, world!\"".to_owned()));
 /// ```
 pub type KeyOption = (String, Option<String>);
 /// A list of `KeyOption` structs, suitable for placement in front of an
 /// `AuthorizedKey`.
 pub type KeyOptions = Vec<KeyOption>;
 
+/// A `KeyOption` whose name is known to `sshd`, with its value parsed.
+///
+/// Convert from a `KeyOption` with `TryFrom`, and back with `From`.
+/// Converting back writes the option in its canonical form, which is
+/// not always the text it was parsed from.
+///
+/// ```
+/// use authorized_keys::openssh::v2::{KeyOption, TypedKeyOption};
+/// use std::convert::TryFrom;
+///
+/// let option: KeyOption = ("tunnel".to_owned(), Some("1".to_owned()));
+///
+/// assert_eq!(Ok(TypedKeyOption::Tunnel(Some(1))), TypedKeyOption::try_from(option));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedKeyOption {
+    /// `agent-forwarding`: permit agent forwarding (undoing `restrict`)
+    AgentForwarding,
+    /// `cert-authority`: the key is a certificate authority
+    CertAuthority,
+    /// `command="..."`: run this command whenever the key is used
+    Command(String),
+    /// `environment="NAME=value"`: set an environment variable
+    Environment {
+        /// Name of the variable
+        name: String,
+        /// Value of the variable
+        value: String,
+    },
+    /// `expiry-time="..."`: the key may not be used after this time
+    ExpiryTime(ExpiryTime),
+    /// `from="..."`: patterns the client's address or name must match
+    From(HostPatternList),
+    /// `no-agent-forwarding`: forbid agent forwarding
+    NoAgentForwarding,
+    /// `no-port-forwarding`: forbid port forwarding
+    NoPortForwarding,
+    /// `no-pty`: forbid pseudo-terminal allocation
+    NoPty,
+    /// `no-touch-required`: security keys need not be touched
+    NoTouchRequired,
+    /// `no-user-rc`: forbid running `~/.ssh/rc`
+    NoUserRc,
+    /// `no-verify-required`: security keys need not verify the user
+    /// (undoing `verify-required`)
+    NoVerifyRequired,
+    /// `no-X11-forwarding`: forbid X11 forwarding
+    NoX11Forwarding,
+    /// `permitlisten="..."`: permit remote forwarding on this port
+    PermitListen(ForwardSpec),
+    /// `permitopen="..."`: permit local forwarding to this destination
+    PermitOpen(ForwardSpec),
+    /// `port-forwarding`: permit port forwarding (undoing `restrict`)
+    PortForwarding,
+    /// `principals="..."`: principals accepted for certificates
+    Principals(Vec<String>),
+    /// `pty`: permit pseudo-terminal allocation (undoing `restrict`)
+    Pty,
+    /// `restrict`: forbid forwarding, pseudo-terminals and `~/.ssh/rc`
+    Restrict,
+    /// `touch-required`: security keys must be touched (undoing
+    /// `no-touch-required`)
+    TouchRequired,
+    /// `tunnel="..."`: force this tunnel device, or `None` for any
+    Tunnel(Option<u32>),
+    /// `user-rc`: permit running `~/.ssh/rc` (undoing `restrict`)
+    UserRc,
+    /// `verify-required`: security keys must verify the user
+    VerifyRequired,
+    /// `X11-forwarding`: permit X11 forwarding (undoing `restrict`)
+    X11Forwarding,
+    /// Any option not known to `sshd`, kept as it was written
+    Unknown(String, Option<String>),
+}
+
+/// An IP network: an address, and how many of its leading bits are
+/// significant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    /// Address of the network, with no bits set beyond its prefix
+    pub address: IpAddr,
+    /// Length of the network prefix, in bits
+    pub prefix_len: u8,
+}
+
+/// One entry in a `from` pattern list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPattern {
+    /// Whether the entry is negated with a leading "!"
+    pub negated: bool,
+    /// The entry as written, without any leading "!"
+    pub pattern: String,
+    /// The network described by the entry, if it is an address or CIDR
+    /// block. Client addresses are matched against it instead of the
+    /// pattern.
+    pub network: Option<IpNetwork>,
+}
+
+/// A comma-separated list of host patterns, as given to `from="..."`.
+///
+/// Entries may be host names or addresses with `*` and `?` wildcards, or
+/// CIDR blocks such as `10.0.0.0/8`, and are negated with "!".
+///
+/// ```
+/// use authorized_keys::openssh::v2::HostPatternList;
+///
+/// let patterns: HostPatternList = "*.example.com,10.0.0.0/8,!10.0.0.1".parse().unwrap();
+///
+/// assert!(patterns.matches("10.2.3.4".parse().unwrap(), None));
+/// assert!(!patterns.matches("10.0.0.1".parse().unwrap(), Some("host.example.com")));
+/// assert!(patterns.matches("192.0.2.1".parse().unwrap(), Some("host.example.com")));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPatternList {
+    /// Entries of the list, in the order they were written
+    pub patterns: Vec<HostPattern>,
+}
+
+/// The port of a forwarding permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardPort {
+    /// Any port, written `*`
+    Any,
+    /// Only this port
+    Port(u16),
+}
+
+/// The host and port permitted by a `permitopen` or `permitlisten`
+/// option, written `host:port` (or `[address]:port` for IPv6).
+///
+/// A host of `*` permits any host. For `permitlisten` the host may also
+/// contain `*` and `?` wildcards, and a bare port is read as `*:port`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardSpec {
+    /// Host name or address, without any brackets
+    pub host: String,
+    /// Port number, or any port
+    pub port: ForwardPort,
+}
+
+/// A time given to `expiry-time`, written `YYYYMMDD[HHMM[SS]]` and
+/// followed by `Z` if it is in UTC.
+///
+/// `sshd` reads times without `Z` in the server's local time zone, which
+/// is not known here; they are treated as UTC.
+///
+/// ```
+/// use authorized_keys::openssh::v2::ExpiryTime;
+///
+/// let time: ExpiryTime = "203001011200Z".parse().unwrap();
+///
+/// assert_eq!(1_893_499_200, time.unix_time);
+/// assert_eq!("203001011200Z", time.to_string());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryTime {
+    /// Seconds since the Unix epoch, reading the time as UTC
+    pub unix_time: u64,
+    /// Whether the time is marked as UTC
+    pub utc: bool,
+}
+
+/// The tunnel device a key forces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForcedTunnel {
+    /// `tunnel="any"`: `sshd` picks the next free device, and refuses a
+    /// client asking for a particular one
+    Any,
+    /// Only this device
+    Device(u32),
+}
+
+/// What a key actually permits once `sshd` has applied all of its
+/// options, in order.
+///
+/// `restrict` turns off all five capabilities, and options such as `pty`
+/// or `no-pty` then turn one on or off, with the last option written
+/// for a capability taking effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectivePermissions {
+    /// Whether agent forwarding is permitted
+    pub agent_forwarding: bool,
+    /// Whether port forwarding is permitted
+    pub port_forwarding: bool,
+    /// Whether X11 forwarding is permitted
+    pub x11_forwarding: bool,
+    /// Whether a pseudo-terminal may be allocated
+    pub pty: bool,
+    /// Whether `~/.ssh/rc` is run
+    pub user_rc: bool,
+    /// Command run in place of whatever the client asks for
+    pub forced_command: Option<String>,
+    /// Tunnel device used in place of whatever the client asks for
+    pub forced_tunnel: Option<ForcedTunnel>,
+    /// Environment variables set, if `sshd` has `PermitUserEnvironment`
+    /// enabled, in the order they are set
+    pub environment: Vec<(String, String)>,
+    /// Patterns the client's address or host name must match
+    pub from: Option<HostPatternList>,
+    /// Destinations local forwards are limited to (unlimited if empty)
+    pub permit_open: Vec<ForwardSpec>,
+    /// Addresses remote forwards are limited to (unlimited if empty)
+    pub permit_listen: Vec<ForwardSpec>,
+    /// Time after which the key is no longer accepted
+    pub expiry_time: Option<ExpiryTime>,
+    /// Whether a security key must be touched to use it; never true for
+    /// other keys
+    pub touch_required: bool,
+    /// Whether a security key must verify the user (e.g. with a PIN) to
+    /// use it; never true for other keys
+    pub verify_required: bool,
+    /// Whether the key is trusted as a certificate authority, rather
+    /// than for logging in with directly
+    pub cert_authority: bool,
+    /// Principals a certificate signed by this authority must name one
+    /// of (otherwise, the user's name)
+    pub principals: Option<Vec<String>>,
+}
+
+/// A release of OpenSSH, such as 8.2 (the portable release number, as
+/// in 8.2p1, makes no difference to what it accepts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OpensshVersion {
+    /// Major version number
+    pub major: u32,
+    /// Minor version number
+    pub minor: u32,
+}
+
+impl OpensshVersion {
+    #[must_use]
+    /// Create a representation of an OpenSSH release
+    pub fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+}
+
 /// Represents a public key for authorization
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct PublicKey {
@@ -38,9 +290,225 @@ impl PublicKey {
     }
 }
 
+/// The components of a public key, decoded from its binary data (the
+/// SSH wire format of RFC 4253). Integers are unsigned and big-endian,
+/// without leading zeroes.
+///
+/// ```
+/// use authorized_keys::openssh::v2::{KeyAuthorization, KeyData};
+///
+/// let authorization: KeyAuthorization = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM"
+///     .parse()
+///     .unwrap();
+///
+/// match authorization.key.key_data().unwrap() {
+///     KeyData::Ed25519 { key } => assert_eq!(0x68, key[0]),
+///     other => panic!("unexpected key: {:?}", other),
+/// }
+/// ```
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyData {
+    /// A DSA key
+    Dsa {
+        /// Prime modulus
+        p: Vec<u8>,
+        /// Prime divisor of `p - 1`
+        q: Vec<u8>,
+        /// Generator
+        g: Vec<u8>,
+        /// Public value
+        y: Vec<u8>,
+    },
+    /// An ECDSA key
+    Ecdsa {
+        /// Name of the curve (e.g. `nistp256`)
+        curve: String,
+        /// Public point, encoded as in SEC 1
+        point: Vec<u8>,
+    },
+    /// An Ed25519 key
+    Ed25519 {
+        /// Public key
+        key: [u8; 32],
+    },
+    /// An RSA key
+    Rsa {
+        /// Public exponent
+        exponent: Vec<u8>,
+        /// Modulus
+        modulus: Vec<u8>,
+    },
+    /// An ECDSA security key
+    SkEcdsa {
+        /// Name of the curve (e.g. `nistp256`)
+        curve: String,
+        /// Public point, encoded as in SEC 1
+        point: Vec<u8>,
+        /// Application the key was registered for (e.g. `ssh:`)
+        application: String,
+    },
+    /// An Ed25519 security key
+    SkEd25519 {
+        /// Public key
+        key: [u8; 32],
+        /// Application the key was registered for (e.g. `ssh:`)
+        application: String,
+    },
+    /// An OpenSSH certificate
+    Certificate(Box<Certificate>),
+}
+
+/// An OpenSSH certificate, as described in OpenSSH's `PROTOCOL.certkeys`.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// Random value chosen by the certificate authority
+    pub nonce: Vec<u8>,
+    /// The key being certified
+    pub key: KeyData,
+    /// Serial number
+    pub serial: u64,
+    /// Whether the certificate is for a user or a host
+    pub cert_type: CertificateType,
+    /// Identifier chosen by the certificate authority, used in logs
+    pub key_id: String,
+    /// Users or hosts the certificate is valid for (any, if empty)
+    pub principals: Vec<String>,
+    /// Time the certificate is valid from, in seconds since the epoch
+    pub valid_after: u64,
+    /// Time the certificate is valid until, in seconds since the epoch
+    pub valid_before: u64,
+    /// Options which must be understood for the certificate to be used,
+    /// with their data
+    pub critical_options: Vec<(String, Vec<u8>)>,
+    /// Optional extensions, with their data
+    pub extensions: Vec<(String, Vec<u8>)>,
+    /// Binary data of the certificate authority's public key
+    pub signature_key: Vec<u8>,
+    /// Certificate authority's signature
+    pub signature: Vec<u8>,
+}
+
+/// A fingerprint of a public key's data, written as `ssh-keygen -l`
+/// shows it (e.g. `SHA256:` followed by unpadded base64, or `MD5:`
+/// followed by hex bytes separated by colons).
+///
+/// ```
+/// use authorized_keys::openssh::v2::{Fingerprint, HashAlg};
+///
+/// let fingerprint: Fingerprint = "SHA256:khAC3AmpVGUK3vjBz9U9Yd5Zzy8rPjkTCHkZ/vTZXTA".parse().unwrap();
+///
+/// assert_eq!(HashAlg::Sha256, fingerprint.alg);
+/// assert_eq!(32, fingerprint.digest.len());
+/// ```
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// Hash algorithm used
+    pub alg: HashAlg,
+    /// Digest of the key's data
+    pub digest: Vec<u8>,
+}
+
+/// Whether a certificate is for a user or a host.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateType {
+    /// A user certificate, for logging in
+    User,
+    /// A host certificate, identifying a server
+    Host,
+}
+
+/// Family of algorithm a key uses, whether it is held on a security key
+/// or certified.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFamily {
+    /// DSA (`ssh-dss`)
+    Dsa,
+    /// ECDSA over one of the NIST curves
+    Ecdsa,
+    /// Ed25519
+    Ed25519,
+    /// RSA
+    Rsa,
+}
+
+/// How a key measures up against a `KeyPolicy`, from best to worst.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyStatus {
+    /// The key meets the policy
+    Ok,
+    /// The key's algorithm is allowed, but the key is too small
+    Weak,
+    /// The key's algorithm should no longer be used
+    Deprecated,
+}
+
+/// The strength of a key, as assessed against a `KeyPolicy`.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyStrength {
+    /// Family of algorithm the key uses
+    pub family: KeyFamily,
+    /// Size of the key in bits, as `ssh-keygen -l` shows it
+    pub bits: usize,
+    /// Effective security of the key in bits (e.g. 112 for a 2048 bit
+    /// RSA key)
+    pub security_bits: usize,
+    /// Whether the key meets the policy
+    pub status: KeyStatus,
+}
+
+/// Thresholds for assessing the strength of keys. The default policy
+/// treats RSA keys under 2048 bits as weak and DSA keys as deprecated;
+/// `KeyPolicy::strict` also deprecates ECDSA keys, which use NIST
+/// curves.
+#[cfg(feature = "key_encoding")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPolicy {
+    /// Smallest RSA or DSA key, in bits, which is not weak
+    pub min_modulus_bits: usize,
+    /// Whether DSA keys are deprecated
+    pub dsa_deprecated: bool,
+    /// Whether ECDSA keys are deprecated
+    pub nist_curves_deprecated: bool,
+}
+
+#[cfg(feature = "key_encoding")]
+impl Default for KeyPolicy {
+    fn default() -> Self {
+        Self {
+            min_modulus_bits: 2048,
+            dsa_deprecated: true,
+            nist_curves_deprecated: false,
+        }
+    }
+}
+
+#[cfg(feature = "key_encoding")]
+impl KeyPolicy {
+    #[must_use]
+    /// A stricter policy, which treats RSA keys under 3072 bits as weak,
+    /// and DSA and ECDSA keys as deprecated
+    pub fn strict() -> Self {
+        Self {
+            min_modulus_bits: 3072,
+            dsa_deprecated: true,
+            nist_curves_deprecated: true,
+        }
+    }
+}
+
 /// Represents the format of a key in an OpenSSH v2 `authorized_keys`
 /// file.
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+///
+/// Authorizations parsed from text remember their original formatting,
+/// which is not considered when comparing them.
+#[derive(Debug, Default, Clone)]
 pub struct KeyAuthorization {
     /// Options applied to the key
     pub options: KeyOptions,
@@ -48,32 +516,178 @@ pub struct KeyAuthorization {
     pub key: PublicKey,
     /// Comments written at the end of the `authorized_keys` line
     pub comments: String,
+    /// The line this authorization was parsed from, if any
+    pub(crate) source: Option<SourceLine>,
+}
+
+impl PartialEq for KeyAuthorization {
+    fn eq(&self, other: &Self) -> bool {
+        self.options == other.options && self.key == other.key && self.comments == other.comments
+    }
+}
+
+impl Eq for KeyAuthorization {}
+
+/// The original text of a parsed key authorization, and where each of
+/// its parts was found within it.
+///
+/// Spans are byte ranges into the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub(crate) text: String,
+    pub(crate) options: Range<usize>,
+    pub(crate) key_type: Range<usize>,
+    pub(crate) encoded_key: Range<usize>,
+    pub(crate) comments: Range<usize>,
+}
+
+/// A `KeyOption` borrowed from the text it was parsed from. The value is
+/// only copied if it has to be unescaped.
+pub type KeyOptionRef<'a> = (&'a str, Option<Cow<'a, str>>);
+
+/// `KeyOptions` borrowed from the text they were parsed from.
+///
+/// Only the text of the option list is kept; the options themselves
+/// are parsed from it as they are iterated over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOptionsRef<'a> {
+    pub(crate) text: &'a str,
+}
+
+/// Iterator over the options in a `KeyOptionsRef`.
+#[derive(Debug, Clone)]
+pub struct KeyOptionsIter<'a> {
+    pub(crate) rest: &'a str,
+}
+
+/// A `PublicKey` borrowed from the text it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeyRef<'a> {
+    /// Type of key, as written (e.g. `ssh-rsa`)
+    pub key_type: &'a str,
+    /// Public key data, base64 encoded
+    pub encoded_key: &'a str,
+}
+
+/// A `KeyAuthorization` borrowed from the line it was parsed from.
+///
+/// Parsing one does not allocate, which suits scanning large numbers of
+/// keys; use `to_authorization` to keep or edit it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAuthorizationRef<'a> {
+    /// Options applied to the key
+    pub options: KeyOptionsRef<'a>,
+    /// Public key type and data
+    pub key: PublicKeyRef<'a>,
+    /// Comments written at the end of the `authorized_keys` line
+    pub comments: &'a str,
+}
+
+/// A comment line in an `authorized_keys` file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// Whitespace written before the "#"
+    pub indent: String,
+    /// Text written after the "#"
+    pub text: String,
+}
+
+impl Comment {
+    #[must_use]
+    /// Create an unindented comment with the given text
+    pub fn new(text: String) -> Self {
+        Self {
+            indent: String::new(),
+            text,
+        }
+    }
 }
 
 /// Represents a valid line in an `authorized_keys` file.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KeysFileLine {
-    /// A comment line: begins with a "#", or is only whitespace.
-    Comment(String),
+    /// An empty line, or one which is only whitespace.
+    Blank(String),
+    /// A comment line: the first non-whitespace is a "#".
+    Comment(Comment),
     /// An authorized key line.
     Key(KeyAuthorization),
+    /// A line which could not be parsed, kept by lenient parsing.
+    Invalid {
+        /// Original text of the line
+        text: String,
+        /// Why the line could not be parsed
+        error: ParseError,
+    },
+}
+
+/// The style of line ending used in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as written on Unix
+    Lf,
+    /// `\r\n`, as written on Windows
+    CrLf,
+}
+
+impl Default for LineEnding {
+    fn default() -> Self {
+        LineEnding::Lf
+    }
 }
 
 /// Represents an `authorized_keys` file.
-#[derive(Debug, Default, Clone)]
+///
+/// Parsed files remember the line ending after each line, their byte
+/// order mark and whether they ended with a newline, and are written
+/// back the same way.
+#[derive(Debug, Clone)]
 pub struct KeysFile {
     /// Lines of the `authorized_keys` file
     pub lines: Vec<KeysFileLine>,
+    /// Line ending written after lines without one in `line_endings`,
+    /// detected from the first line ending in a parsed file
+    pub line_ending: LineEnding,
+    /// Line ending read after each line of a parsed file, in order.
+    /// Remove the matching entry when removing a line, so that the
+    /// lines after it keep theirs.
+    pub line_endings: Vec<LineEnding>,
+    /// Whether the file starts with a UTF-8 byte order mark
+    pub byte_order_mark: bool,
+    /// Whether the last line is followed by a line ending
+    pub final_newline: bool,
+}
+
+impl Default for KeysFile {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            line_ending: LineEnding::default(),
+            line_endings: Vec::new(),
+            byte_order_mark: false,
+            final_newline: true,
+        }
+    }
 }
 
 impl FromIterator<KeysFileLine> for KeysFile {
     fn from_iter<I: IntoIterator<Item = KeysFileLine>>(i: I) -> Self {
         Self {
             lines: i.into_iter().collect::<Vec<_>>(),
+            ..Self::default()
         }
     }
 }
 
+impl<'a, 'b> IntoIterator for &'b KeyOptionsRef<'a> {
+    type Item = KeyOptionRef<'a>;
+    type IntoIter = KeyOptionsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl IntoIterator for KeysFile {
     type Item = KeysFileLine;
     type IntoIter = ::std::vec::IntoIter<KeysFileLine>;
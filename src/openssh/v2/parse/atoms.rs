@@ -1,23 +1,39 @@
+use super::super::errors::Expected;
+use super::errors::{expecting, ParseResult};
 use nom::branch::alt;
-use nom::bytes::complete::{escaped, is_a, is_not, tag, take, take_while1};
-use nom::character::complete::{anychar, char, space0};
-use nom::combinator::{recognizec, value};
+use nom::bytes::complete::{is_a, is_not, tag, take, take_while1};
+use nom::character::complete::{char, space0};
+use nom::combinator::{recognize, recognizec, value};
 use nom::error::{ErrorKind, ParseError};
-use nom::multi::count;
+use nom::multi::{count, many0};
 use nom::sequence::{delimitedc, pairc};
-use nom::IResult;
 
 /// Parse valid whitespace.
-pub(crate) fn whitespace(input: &str) -> IResult<&str, &str> {
-    is_a(" \t")(input)
+pub(crate) fn whitespace(input: &str) -> ParseResult<&str> {
+    expecting(Expected::Whitespace, is_a(" \t"))(input)
 }
 
 /// Parse an identifier from the start of the input.
 ///
 /// An identifier begins with an alphabetic character, ends with an alphanumeric
 /// character, and can have alphanumeric characters (or dashes) in the middle.
-pub(crate) fn identifier(input: &str) -> IResult<&str, &str> {
-    let res = take_while1(|c: char| c.is_alphanumeric() || c == '-')(input)?;
+pub(crate) fn identifier(input: &str) -> ParseResult<&str> {
+    identifier_with(input, "-")
+}
+
+/// Parse an extended identifier from the start of the input.
+///
+/// Extended identifiers follow the same rules as identifiers, but can also
+/// have `@` or `.` characters in the middle (e.g.
+/// `sk-ssh-ed25519@openssh.com`).
+pub(crate) fn extended_identifier(input: &str) -> ParseResult<&str> {
+    identifier_with(input, "-@.")
+}
+
+/// Parse an identifier which can contain the given punctuation in the
+/// middle.
+fn identifier_with<'a>(input: &'a str, punctuation: &str) -> ParseResult<'a, &'a str> {
+    let res = take_while1(|c: char| c.is_alphanumeric() || punctuation.contains(c))(input)?;
 
     let first_char = res.1.chars().nth(0).unwrap();
     let last_char = res.1.chars().last().unwrap();
@@ -32,14 +48,18 @@ pub(crate) fn identifier(input: &str) -> IResult<&str, &str> {
     Ok(res)
 }
 
-/// Parse an escapable string.
-pub(crate) fn string(input: &str) -> IResult<&str, &str> {
+/// Parse a quoted string, returning its text as written. As in `sshd`,
+/// `\"` is an escaped double-quote and any other backslash is literal.
+pub(crate) fn string(input: &str) -> ParseResult<&str> {
     alt((value("", tag(r#""""#)), |inner| {
         delimitedc(
             inner,
-            char('"'),
-            escaped(is_not(r#"\""#), '\\', anychar),
-            char('"'),
+            expecting(Expected::OpeningQuote, char('"')),
+            expecting(
+                Expected::ClosingQuote,
+                recognize(many0(alt((is_not(r#"\""#), tag(r#"\""#), tag("\\"))))),
+            ),
+            expecting(Expected::ClosingQuote, char('"')),
         )
     }))(input)
 }
@@ -52,7 +72,11 @@ fn is_base64_body_char(input: char) -> bool {
 }
 
 /// Parse a valid base64 padded string from the start of input.
-pub(crate) fn base64(input: &str) -> IResult<&str, &str> {
+pub(crate) fn base64(input: &str) -> ParseResult<&str> {
+    expecting(Expected::Base64, base64_body)(input)
+}
+
+fn base64_body(input: &str) -> ParseResult<&str> {
     let base_res = take_while1(is_base64_body_char)(input)?;
 
     let remainder = base_res.1.len() % 4;
@@ -80,7 +104,7 @@ pub(crate) fn base64(input: &str) -> IResult<&str, &str> {
 }
 
 /// Parse the rest of the line (or input) as comments.
-pub(crate) fn comments(input: &str) -> IResult<&str, &str> {
+pub(crate) fn comments(input: &str) -> ParseResult<&str> {
     alt((is_not("\r\n"), space0))(input)
 }
 
@@ -110,6 +134,30 @@ mod tests {
         assert_cases_err(identifier, vec!["1a", "-A", "A-", "@ca-certificate"]);
     }
 
+    #[test]
+    fn parses_extended_identifiers() {
+        assert_that_cases(
+            |i| extended_identifier(i).unwrap().1,
+            as_expected,
+            vec![
+                ("ssh-ed25519 ", "ssh-ed25519"),
+                (
+                    "sk-ssh-ed25519@openssh.com AAAA",
+                    "sk-ssh-ed25519@openssh.com",
+                ),
+                ("a.b@c", "a.b@c"),
+            ],
+        )
+    }
+
+    #[test]
+    fn rejects_invalid_extended_identifiers() {
+        assert_cases_err(
+            extended_identifier,
+            vec!["@openssh.com", ".com", "sk-ssh-ed25519@", "openssh."],
+        );
+    }
+
     #[test]
     fn parses_strings() {
         assert_that_cases(
@@ -119,6 +167,7 @@ mod tests {
                 (r#""""#, ""),
                 (r#""uptime""#, "uptime"),
                 (r#""echo \"Hello,\nworld!\"""#, r#"echo \"Hello,\nworld!\""#),
+                (r#""C:\dir""#, r#"C:\dir"#),
             ],
         )
     }
@@ -134,6 +183,8 @@ mod tests {
                 (r#""no end in sight"#),
                 // unterminated due to escaping
                 (r#""\""#),
+                // a backslash before the closing quote escapes it
+                (r#""a\\""#),
             ],
         );
     }
@@ -0,0 +1,81 @@
+use super::super::errors::{Expected, ParseError};
+use nom::error::{ErrorKind, ParseError as NomParseError};
+use nom::IResult;
+
+/// Error produced by the line parsers, pointing into the parsed input.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RawError<'a> {
+    /// Remaining input at the point of failure
+    pub(crate) input: &'a str,
+    /// What was expected at that point, once known
+    pub(crate) expected: Option<Expected>,
+}
+
+pub(crate) type ParseResult<'a, O> = IResult<&'a str, O, RawError<'a>>;
+
+impl<'a> RawError<'a> {
+    fn or_expected(self, expected: Expected) -> Self {
+        Self {
+            input: self.input,
+            expected: self.expected.or_else(|| Some(expected)),
+        }
+    }
+
+    /// Convert into a `ParseError`, given the full line which was being
+    /// parsed.
+    pub(crate) fn into_parse_error(self, line_no: usize, line: &str) -> ParseError {
+        let offset = line.len() - self.input.len();
+        let at_whitespace = self.input.starts_with(char::is_whitespace);
+        let token_len = self
+            .input
+            .find(|c: char| c.is_whitespace() != at_whitespace)
+            .unwrap_or_else(|| self.input.len());
+
+        ParseError::Syntax {
+            line: line_no,
+            column: line[..offset].chars().count() + 1,
+            token: self.input[..token_len].to_owned(),
+            // failures that escape unlabelled happened before anything
+            // on the line was recognised
+            expected: self.expected.unwrap_or(Expected::KeyType),
+        }
+    }
+}
+
+impl<'a> NomParseError<&'a str> for RawError<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Self {
+            input,
+            expected: None,
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Of two failed alternatives, report the one which got furthest
+    /// through the input.
+    fn or(self, other: Self) -> Self {
+        if self.input.len() < other.input.len() {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+/// Label any unlabelled error from `parser` with what was expected.
+pub(crate) fn expecting<'a, O, F>(
+    expected: Expected,
+    parser: F,
+) -> impl Fn(&'a str) -> ParseResult<'a, O>
+where
+    F: Fn(&'a str) -> ParseResult<'a, O>,
+{
+    move |input| match parser(input) {
+        Err(nom::Err::Error(err)) => Err(nom::Err::Error(err.or_expected(expected))),
+        Err(nom::Err::Failure(err)) => Err(nom::Err::Failure(err.or_expected(expected))),
+        res => res,
+    }
+}
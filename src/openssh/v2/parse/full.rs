@@ -1,36 +1,56 @@
-use super::super::models::{KeyAuthorization, KeyOptions};
+use super::super::models::{KeyAuthorizationRef, KeyOptionsRef, SourceLine};
 use super::atoms::{comments, whitespace};
+use super::errors::ParseResult;
 use super::mapped::*;
-use nom::{branch::alt, sequence::tuple, IResult};
+use nom::{branch::alt, sequence::tuple, Offset};
+use std::ops::Range;
 
-pub(crate) fn key_authorization_without_options(input: &str) -> IResult<&str, KeyAuthorization> {
-    let (input, (parsed_public_key, parsed_comments)) = tuple((public_key, comments))(input)?;
+fn span(input: &str, part: &str) -> Range<usize> {
+    let start = input.offset(part);
+
+    start..start + part.len()
+}
+
+/// Record where each part of the key authorization was found in `input`,
+/// which it must have been parsed from.
+pub(crate) fn source_line(input: &str, rest: &str, parsed: &KeyAuthorizationRef) -> SourceLine {
+    SourceLine {
+        text: input[..input.len() - rest.len()].to_owned(),
+        options: span(input, parsed.options.as_str()),
+        key_type: span(input, parsed.key.key_type),
+        encoded_key: span(input, parsed.key.encoded_key),
+        comments: span(input, parsed.comments),
+    }
+}
+
+pub(crate) fn key_authorization_without_options(input: &str) -> ParseResult<KeyAuthorizationRef> {
+    let (rest, (parsed_public_key, parsed_comments)) = tuple((public_key, comments))(input)?;
 
     Ok((
-        input,
-        KeyAuthorization {
-            options: KeyOptions::new(),
+        rest,
+        KeyAuthorizationRef {
+            options: KeyOptionsRef { text: &input[..0] },
             key: parsed_public_key,
-            comments: parsed_comments.trim_start().to_owned(),
+            comments: parsed_comments.trim_start(),
         },
     ))
 }
 
-pub(crate) fn key_authorization_with_options(input: &str) -> IResult<&str, KeyAuthorization> {
-    let (input, (options, _, parsed_public_key, parsed_comments)) =
+pub(crate) fn key_authorization_with_options(input: &str) -> ParseResult<KeyAuthorizationRef> {
+    let (rest, (parsed_options, _, parsed_public_key, parsed_comments)) =
         tuple((key_options, whitespace, public_key, comments))(input)?;
 
     Ok((
-        input,
-        KeyAuthorization {
-            options,
+        rest,
+        KeyAuthorizationRef {
+            options: parsed_options,
             key: parsed_public_key,
-            comments: parsed_comments.trim_start().to_owned(),
+            comments: parsed_comments.trim_start(),
         },
     ))
 }
 
-pub(crate) fn key_authorization(input: &str) -> IResult<&str, KeyAuthorization> {
+pub(crate) fn key_authorization(input: &str) -> ParseResult<KeyAuthorizationRef> {
     alt((
         key_authorization_without_options,
         key_authorization_with_options,
@@ -39,14 +59,14 @@ pub(crate) fn key_authorization(input: &str) -> IResult<&str, KeyAuthorization>
 
 #[cfg(test)]
 mod tests {
-    use super::super::super::models::{KeyType, PublicKey};
+    use super::super::super::models::{KeyAuthorization, KeyOptions, KeyType, PublicKey};
     use super::*;
     use crate::testing::*;
 
     #[test]
     fn it_parses_full_authorizations() {
         assert_that_cases(
-            |i| key_authorization(i).unwrap().1,
+            |i| key_authorization(i).unwrap().1.to_authorization(),
             as_expected,
             vec![
                 (
@@ -58,6 +78,7 @@ mod tests {
                             encoded_key: "foobar==".to_owned(),
                         },
                         comments: "".to_owned(),
+                        source: None,
                     },
                 ),
                 (
@@ -69,6 +90,7 @@ mod tests {
                             encoded_key: "istestbase64".to_owned(),
                         },
                         comments: "".to_owned(),
+                        source: None,
                     },
                 ),
                 (
@@ -80,6 +102,7 @@ mod tests {
                             encoded_key: "foobar==".to_owned(),
                         },
                         comments: "now with comments".to_owned(),
+                        source: None,
                     },
                 ),
                 (
@@ -91,9 +114,63 @@ mod tests {
                             encoded_key: "istestbase64".to_owned(),
                         },
                         comments: "also with comments".to_owned(),
+                        source: None,
+                    },
+                ),
+                (
+                    "no-touch-required sk-ecdsa-sha2-nistp256@openssh.com istestbase64 security key",
+                    KeyAuthorization {
+                        options: vec![("no-touch-required".to_owned(), None)],
+                        key: PublicKey {
+                            key_type: KeyType::SkEcdsaSha2Nistp256,
+                            encoded_key: "istestbase64".to_owned(),
+                        },
+                        comments: "security key".to_owned(),
+                        source: None,
+                    },
+                ),
+                (
+                    "restrict ssh-xmss@openssh.com istestbase64",
+                    KeyAuthorization {
+                        options: vec![("restrict".to_owned(), None)],
+                        key: PublicKey {
+                            key_type: KeyType::Other("ssh-xmss@openssh.com".to_owned()),
+                            encoded_key: "istestbase64".to_owned(),
+                        },
+                        comments: "".to_owned(),
+                        source: None,
                     },
                 ),
             ],
         );
     }
+
+    #[test]
+    fn it_records_where_each_part_was_found() {
+        let input = "restrict,pty  ssh-ed25519 foobar==   a comment \nnext line";
+        let (rest, parsed) = key_authorization(input).unwrap();
+        let source = source_line(input, rest, &parsed);
+
+        assert_eq!("\nnext line", rest);
+        assert_eq!(
+            "restrict,pty  ssh-ed25519 foobar==   a comment ",
+            source.text
+        );
+        assert_eq!("restrict,pty", &source.text[source.options]);
+        assert_eq!("ssh-ed25519", &source.text[source.key_type]);
+        assert_eq!("foobar==", &source.text[source.encoded_key]);
+        assert_eq!("a comment ", &source.text[source.comments]);
+    }
+
+    #[test]
+    fn it_records_where_each_part_was_found_without_options() {
+        let input = "ssh-ed25519\tfoobar==";
+        let (rest, parsed) = key_authorization(input).unwrap();
+        let source = source_line(input, rest, &parsed);
+
+        assert_eq!(0..0, source.options);
+        assert_eq!(0..11, source.key_type);
+        assert_eq!(12..20, source.encoded_key);
+        assert_eq!(20..20, source.comments);
+    }
 }
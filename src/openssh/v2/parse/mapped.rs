@@ -1,38 +1,35 @@
-use super::super::models::{KeyOptions, KeyType, PublicKey};
+use super::super::errors::Expected;
+use super::super::models::{KeyOptionsRef, PublicKeyRef};
 use super::atoms::*;
+use super::errors::{expecting, ParseResult};
 use super::parts::*;
-use nom::{combinator::map_resc, sequence::tuple, IResult};
-use std::borrow::ToOwned;
+use nom::sequence::tuple;
 
-pub(crate) fn key_type(input: &str) -> IResult<&str, KeyType> {
-    map_resc(input, identifier, str::parse)
+pub(crate) fn key_type(input: &str) -> ParseResult<&str> {
+    expecting(Expected::KeyType, extended_identifier)(input)
 }
 
-pub(crate) fn public_key(input: &str) -> IResult<&str, PublicKey> {
+pub(crate) fn public_key(input: &str) -> ParseResult<PublicKeyRef> {
     let (input, (parsed_key_type, _, encoded_key)) = tuple((key_type, whitespace, base64))(input)?;
 
     Ok((
         input,
-        PublicKey {
+        PublicKeyRef {
             key_type: parsed_key_type,
-            encoded_key: encoded_key.to_owned(),
+            encoded_key,
         },
     ))
 }
 
-pub(crate) fn key_options(input: &str) -> IResult<&str, KeyOptions> {
-    let (input, parsed_options) = options(input)?;
+pub(crate) fn key_options(input: &str) -> ParseResult<KeyOptionsRef> {
+    let (input, text) = options(input)?;
 
-    let mapped_options = parsed_options
-        .iter()
-        .map(|(name, val)| ((*name).to_string(), val.map(ToOwned::to_owned)))
-        .collect();
-
-    Ok((input, mapped_options))
+    Ok((input, KeyOptionsRef { text }))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::super::models::{KeyType, PublicKey};
     use super::*;
     use crate::testing::*;
 
@@ -43,7 +40,7 @@ mod tests {
     #[test]
     fn parses_all_key_types() {
         assert_that_cases(
-            |i| key_type(&i).unwrap().1,
+            |i| KeyType::from(key_type(&i).unwrap().1),
             as_expected,
             KeyType::name_value_pairs()
                 .into_iter()
@@ -52,10 +49,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_unknown_key_types() {
+        assert_that_cases(
+            |i| KeyType::from(key_type(i).unwrap().1),
+            as_expected,
+            vec![
+                (
+                    "ssh-xmss@openssh.com",
+                    KeyType::Other("ssh-xmss@openssh.com".to_owned()),
+                ),
+                (
+                    "x-vendor-key AAAA",
+                    KeyType::Other("x-vendor-key".to_owned()),
+                ),
+            ],
+        );
+    }
+
     #[test]
     fn parses_public_keys() {
         assert_that_cases(
-            |i| public_key(i).unwrap().1,
+            |i| public_key(i).unwrap().1.to_public_key(),
             as_expected,
             vec![
                 (
@@ -72,6 +87,13 @@ mod tests {
                         encoded_key: "istestbase64".to_owned(),
                     },
                 ),
+                (
+                    "sk-ssh-ed25519@openssh.com foobar==",
+                    PublicKey {
+                        key_type: KeyType::SkSshEd25519,
+                        encoded_key: "foobar==".to_owned(),
+                    },
+                ),
             ],
         );
     }
@@ -79,7 +101,7 @@ mod tests {
     #[test]
     fn parses_option_lists() {
         assert_that_cases(
-            |i| key_options(i).unwrap().1,
+            |i| key_options(i).unwrap().1.to_key_options(),
             as_expected,
             vec![
                 ("restrict", vec![("restrict".to_owned(), None)]),
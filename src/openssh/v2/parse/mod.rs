@@ -1,48 +1,409 @@
 mod atoms;
+mod errors;
 mod full;
 mod mapped;
 mod parts;
+mod values;
 
-use super::models::{KeyAuthorization, KeysFile, KeysFileLine};
+use super::errors::{Expected, ParseError};
+use super::models::{
+    Comment, KeyAuthorization, KeyAuthorizationRef, KeyOptionRef, KeyOptionsIter, KeysFile,
+    KeysFileLine, LineEnding,
+};
+use std::borrow::Cow;
 use std::str::FromStr;
 
+/// Remove the escaping from an option value as written, as `sshd` does:
+/// `\"` becomes `"`, and any other backslash is kept.
+pub(crate) fn unescape(value: &str) -> Cow<str> {
+    if value.contains(r#"\""#) {
+        Cow::Owned(value.replace(r#"\""#, "\""))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Parse a single key authorization line, reporting errors against the
+/// given (1-based) line number. Also returns the unparsed rest of the
+/// line.
+fn key_authorization_ref(
+    line_no: usize,
+    line: &str,
+) -> Result<(&str, KeyAuthorizationRef), ParseError> {
+    let (rest, parsed) = full::key_authorization(line).map_err(|e| match e {
+        nom::Err::Incomplete(_) => unreachable!(),
+        nom::Err::Error(err) | nom::Err::Failure(err) => err.into_parse_error(line_no, line),
+    })?;
+
+    // comments stop at a carriage return, so anything after a lone one
+    // would otherwise be lost
+    if rest.contains(|c| c != '\r' && c != '\n') {
+        let err = errors::RawError {
+            input: rest,
+            expected: Some(Expected::EndOfLine),
+        };
+
+        return Err(err.into_parse_error(line_no, line));
+    }
+
+    Ok((rest, parsed))
+}
+
+/// Parse a single key authorization line into an owned authorization
+/// which remembers its formatting.
+fn key_authorization(line_no: usize, line: &str) -> Result<KeyAuthorization, ParseError> {
+    let (rest, parsed) = key_authorization_ref(line_no, line)?;
+    let mut authorization = parsed.to_authorization();
+    authorization.source = Some(full::source_line(line, rest, &parsed));
+
+    Ok(authorization)
+}
+
+impl<'a> KeyAuthorizationRef<'a> {
+    /// Parse a key authorization line, borrowing each part from it
+    /// rather than copying.
+    ///
+    /// ```
+    /// use authorized_keys::openssh::v2::KeyAuthorizationRef;
+    ///
+    /// let key = KeyAuthorizationRef::parse("restrict,pty ssh-ed25519 AAAA user@host").unwrap();
+    ///
+    /// assert_eq!("ssh-ed25519", key.key.key_type);
+    /// assert_eq!(vec![("restrict", None), ("pty", None)], key.options.iter().collect::<Vec<_>>());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` if the line is not a valid key
+    /// authorization.
+    pub fn parse(line: &'a str) -> Result<Self, ParseError> {
+        key_authorization_ref(1, line).map(|(_, parsed)| parsed)
+    }
+}
+
+impl<'a> Iterator for KeyOptionsIter<'a> {
+    type Item = KeyOptionRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the text has already been parsed, so only ends when empty
+        let (rest, parsed) = parts::option(self.rest).ok()?;
+        // skip the comma separating this option from the next
+        self.rest = rest.get(1..).unwrap_or("");
+
+        Some((parsed.0, parsed.1.map(unescape)))
+    }
+}
+
 impl FromStr for KeyAuthorization {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        full::key_authorization(s)
-            .map(|(_, res)| res)
-            .map_err(|e| match e {
-                nom::Err::Incomplete(_) => unreachable!(),
-                nom::Err::Error(err) | nom::Err::Failure(err) => err.0.to_string(),
-            })
+        key_authorization(1, s)
+    }
+}
+
+/// Parse a single line of an `authorized_keys` file, reporting errors
+/// against the given (1-based) line number.
+pub(crate) fn keys_file_line(line_no: usize, line: &str) -> Result<KeysFileLine, ParseError> {
+    let content = line.trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+    if content.is_empty() {
+        Ok(KeysFileLine::Blank(line.to_owned()))
+    } else if content.starts_with('#') {
+        Ok(KeysFileLine::Comment(Comment {
+            indent: line[..line.len() - content.len()].to_owned(),
+            text: content[1..].to_owned(),
+        }))
+    } else {
+        key_authorization(line_no, line).map(KeysFileLine::Key)
     }
 }
 
+/// Split `body` into lines as `str::lines` does, along with the line
+/// ending after each (`None` for a last line without one).
+fn lines_with_endings(body: &str) -> Vec<(&str, Option<LineEnding>)> {
+    let mut lines = Vec::new();
+    let mut rest = body;
+
+    while let Some(end) = rest.find('\n') {
+        let line = &rest[..end];
+
+        if line.ends_with('\r') {
+            lines.push((&line[..line.len() - 1], Some(LineEnding::CrLf)));
+        } else {
+            lines.push((line, Some(LineEnding::Lf)));
+        }
+        rest = &rest[end + 1..];
+    }
+
+    if !rest.is_empty() {
+        let line = if rest.ends_with('\r') {
+            &rest[..rest.len() - 1]
+        } else {
+            rest
+        };
+
+        lines.push((line, None));
+    }
+
+    lines
+}
+
+/// Detect how the file `s` is laid out, returning an empty file which
+/// records it, along with the text of `s` after any byte order mark.
+fn file_layout(s: &str) -> (KeysFile, &str) {
+    let byte_order_mark = s.starts_with('\u{feff}');
+    let body = if byte_order_mark {
+        &s['\u{feff}'.len_utf8()..]
+    } else {
+        s
+    };
+
+    let line_ending = match body.find('\n') {
+        Some(end) if body[..end].ends_with('\r') => LineEnding::CrLf,
+        _ => LineEnding::Lf,
+    };
+
+    (
+        KeysFile {
+            lines: Vec::new(),
+            line_ending,
+            line_endings: Vec::new(),
+            byte_order_mark,
+            final_newline: body.is_empty() || body.ends_with('\n'),
+        },
+        body,
+    )
+}
+
 impl FromStr for KeysFile {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let in_lines = s.lines().enumerate().collect::<Vec<_>>();
+        let (mut file, body) = file_layout(s);
 
-        let mut lines: Vec<KeysFileLine> = Vec::with_capacity(in_lines.len());
+        for (line_no, (line, line_ending)) in lines_with_endings(body).into_iter().enumerate() {
+            file.lines.push(keys_file_line(line_no + 1, line)?);
+            file.line_endings.extend(line_ending);
+        }
 
-        for (line_no, line) in in_lines {
-            let comment_indicator = line.chars().skip_while(char::is_ascii_whitespace).next();
+        Ok(file)
+    }
+}
 
-            // line was all whitespace, or first non-whitespace was comment char
-            lines.push(
-                if comment_indicator == None || comment_indicator == Some('#') {
-                    KeysFileLine::Comment(line.to_owned())
-                } else {
-                    match line.parse() {
-                        Ok(authorization) => KeysFileLine::Key(authorization),
-                        Err(e) => return Err(format!("failed to parse line {}: {}", line_no, e)),
+impl KeysFile {
+    /// Parse an `authorized_keys` file, keeping any lines which cannot be
+    /// parsed as `KeysFileLine::Invalid` rather than failing (much as
+    /// `sshd` skips them).
+    #[must_use]
+    pub fn parse_lenient(s: &str) -> Self {
+        let (mut file, body) = file_layout(s);
+
+        for (line_no, (line, line_ending)) in lines_with_endings(body).into_iter().enumerate() {
+            file.lines
+                .push(keys_file_line(line_no + 1, line).unwrap_or_else(|error| {
+                    KeysFileLine::Invalid {
+                        text: line.to_owned(),
+                        error,
                     }
-                },
+                }));
+            file.line_endings.extend(line_ending);
+        }
+
+        file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::errors::Expected;
+    use super::*;
+
+    fn syntax_error(line: usize, column: usize, token: &str, expected: Expected) -> ParseError {
+        ParseError::Syntax {
+            line,
+            column,
+            token: token.to_owned(),
+            expected,
+        }
+    }
+
+    #[test]
+    fn it_reports_where_a_line_is_invalid() {
+        let cases = vec![
+            (
+                "ssh-ed25519 foobar==a",
+                syntax_error(1, 21, "a", Expected::Base64),
+            ),
+            (
+                r#"command="uptime ssh-ed25519 foobar=="#,
+                syntax_error(1, 37, "", Expected::ClosingQuote),
+            ),
+            (
+                "command=uptime ssh-ed25519 foobar==",
+                syntax_error(1, 9, "uptime", Expected::OpeningQuote),
+            ),
+            (
+                "restrict,,pty ssh-ed25519 foobar==",
+                syntax_error(1, 10, ",pty", Expected::OptionName),
+            ),
+            (
+                "restrict ssh-ed25519",
+                syntax_error(1, 21, "", Expected::Whitespace),
+            ),
+            (
+                "restrict ssh-ed25519 !foobar",
+                syntax_error(1, 22, "!foobar", Expected::Base64),
+            ),
+            (
+                "ssh-ed25519 foobar== c\rtail",
+                syntax_error(1, 23, "\r", Expected::EndOfLine),
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                Err(expected),
+                input.parse::<KeyAuthorization>(),
+                "{:?}",
+                input
             );
         }
+    }
+
+    #[test]
+    fn it_allows_a_line_ending_after_the_comments() {
+        for input in &["ssh-ed25519 foobar== c\n", "ssh-ed25519 foobar== c\r\n"] {
+            let subject = input.parse::<KeyAuthorization>().unwrap();
+
+            assert_eq!("c", subject.comments);
+            assert_eq!("ssh-ed25519 foobar== c", subject.to_string());
+        }
+    }
+
+    #[test]
+    fn it_reports_the_line_number_of_an_invalid_line() {
+        let subject = "# comment\n\nssh-ed25519 foobar==\nno-pty ssh-ed25519 foo!bar\n"
+            .parse::<KeysFile>()
+            .unwrap_err();
+
+        assert_eq!(syntax_error(4, 23, "!bar", Expected::Base64), subject);
+    }
+
+    #[test]
+    fn it_keeps_invalid_lines_when_lenient() {
+        let input = "# comment\nno-pty ssh-ed25519 foo!bar\nssh-ed25519 foobar==\n";
+        let subject = KeysFile::parse_lenient(input);
+
+        assert_eq!(3, subject.lines.len());
+        assert_eq!(
+            KeysFileLine::Invalid {
+                text: "no-pty ssh-ed25519 foo!bar".to_owned(),
+                error: syntax_error(2, 23, "!bar", Expected::Base64),
+            },
+            subject.lines[1]
+        );
+        assert_eq!(
+            Ok(subject.lines[2].clone()),
+            keys_file_line(3, "ssh-ed25519 foobar==")
+        );
+        assert_eq!(input, subject.to_string());
+    }
+
+    #[test]
+    fn it_parses_borrowed_authorizations_like_owned_ones() {
+        let input = r#"restrict,command="uptime" ssh-ed25519 foobar== me@host"#;
+        let subject = KeyAuthorizationRef::parse(input).unwrap();
+
+        assert_eq!(r#"restrict,command="uptime""#, subject.options.as_str());
+        assert_eq!(
+            vec![
+                ("restrict", None),
+                ("command", Some(Cow::Borrowed("uptime")))
+            ],
+            subject.options.iter().collect::<Vec<_>>()
+        );
+        assert_eq!("ssh-ed25519", subject.key.key_type);
+        assert_eq!("foobar==", subject.key.encoded_key);
+        assert_eq!("me@host", subject.comments);
+        assert_eq!(
+            input.parse::<KeyAuthorization>().unwrap(),
+            subject.to_authorization()
+        );
+        assert_eq!(None, subject.to_authorization().source());
+    }
+
+    #[test]
+    fn it_reports_errors_for_borrowed_authorizations() {
+        assert_eq!(
+            Err(syntax_error(1, 22, "!foobar", Expected::Base64)),
+            KeyAuthorizationRef::parse("restrict ssh-ed25519 !foobar")
+        );
+    }
+
+    #[test]
+    fn it_unescapes_option_values() {
+        let input = r#"command="echo \"hi\" C:\dir\\\"" ssh-ed25519 foobar=="#;
+        let subject = input.parse::<KeyAuthorization>().unwrap();
+
+        assert_eq!(
+            vec![(
+                "command".to_owned(),
+                Some(r#"echo "hi" C:\dir\\""#.to_owned())
+            )],
+            subject.options
+        );
+        assert_eq!(
+            vec![("command", Some(Cow::from(r#"echo "hi" C:\dir\\""#)))],
+            KeyAuthorizationRef::parse(input)
+                .unwrap()
+                .options
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(input), subject.original_text());
+        assert_eq!(input, subject.discard_formatting().to_string());
+    }
+
+    #[test]
+    fn it_separates_blank_lines_from_comments() {
+        assert_eq!(
+            Ok(KeysFileLine::Blank(" \t".to_owned())),
+            keys_file_line(1, " \t")
+        );
+        assert_eq!(
+            Ok(KeysFileLine::Comment(Comment {
+                indent: "  ".to_owned(),
+                text: " admins".to_owned(),
+            })),
+            keys_file_line(1, "  # admins")
+        );
+        assert_eq!(
+            Ok(KeysFileLine::Comment(Comment::new("".to_owned()))),
+            keys_file_line(1, "#")
+        );
+    }
+
+    #[test]
+    fn it_detects_the_layout_of_a_file() {
+        let subject = "\u{feff}# comment\r\nssh-ed25519 foobar==\n"
+            .parse::<KeysFile>()
+            .unwrap();
+
+        assert_eq!(LineEnding::CrLf, subject.line_ending);
+        assert_eq!(vec![LineEnding::CrLf, LineEnding::Lf], subject.line_endings);
+        assert!(subject.byte_order_mark);
+        assert!(subject.final_newline);
+        assert_eq!(
+            KeysFileLine::Comment(Comment::new(" comment".to_owned())),
+            subject.lines[0]
+        );
+
+        let subject = "ssh-ed25519 foobar==".parse::<KeysFile>().unwrap();
 
-        Ok(Self { lines })
+        assert_eq!(LineEnding::Lf, subject.line_ending);
+        assert!(subject.line_endings.is_empty());
+        assert!(!subject.byte_order_mark);
+        assert!(!subject.final_newline);
     }
 }
@@ -1,35 +1,51 @@
+use super::super::errors::Expected;
 use super::atoms::*;
+use super::errors::{expecting, ParseResult};
 use nom::branch::alt;
 use nom::character::complete::char;
-use nom::multi::separated_listc;
-use nom::sequence::separated_pairc;
-use nom::IResult;
+use nom::combinator::{cut, opt, recognize};
+use nom::multi::fold_many0;
+use nom::sequence::{pair, preceded, separated_pairc};
 
-type RawKeyOption<'a> = (&'a str, Option<&'a str>);
+/// An option as written, with its value still escaped.
+type RawOption<'a> = (&'a str, Option<&'a str>);
 
-pub(crate) fn option_without_value(input: &str) -> IResult<&str, RawKeyOption> {
-    let id = identifier(input)?;
+fn option_name(input: &str) -> ParseResult<&str> {
+    expecting(Expected::OptionName, identifier)(input)
+}
+
+pub(crate) fn option_without_value(input: &str) -> ParseResult<RawOption> {
+    let id = option_name(input)?;
 
     Ok((id.0, (id.1, None)))
 }
 
-pub(crate) fn option_with_value(input: &str) -> IResult<&str, RawKeyOption> {
-    let pair = separated_pairc(input, identifier, char('='), string)?;
+/// Parse an option with a value. Once the `=` has been seen, the value
+/// must follow.
+pub(crate) fn option_with_value(input: &str) -> ParseResult<RawOption> {
+    let pair = separated_pairc(input, option_name, char('='), cut(string))?;
     let val = pair.1;
 
     Ok((pair.0, (val.0, Some(val.1))))
 }
 
-pub(crate) fn option(input: &str) -> IResult<&str, RawKeyOption> {
+pub(crate) fn option(input: &str) -> ParseResult<RawOption> {
     alt((option_with_value, option_without_value))(input)
 }
 
-pub(crate) fn options(input: &str) -> IResult<&str, Vec<RawKeyOption>> {
-    separated_listc(input, char(','), option)
+/// Parse a (possibly empty) comma-separated list of options, returning
+/// the text of the list without collecting the options themselves.
+/// Once a comma has been seen, another option must follow.
+pub(crate) fn options(input: &str) -> ParseResult<&str> {
+    recognize(opt(pair(
+        option,
+        fold_many0(preceded(char(','), cut(option)), (), |(), _| ()),
+    )))(input)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::super::models::KeyOptionsIter;
     use super::*;
     use crate::testing::*;
 
@@ -62,14 +78,36 @@ mod tests {
         assert_that_cases(
             |i| options(i).unwrap().1,
             as_expected,
+            vec![
+                ("restrict", "restrict"),
+                ("command=\"uptime\" ssh-rsa", "command=\"uptime\""),
+                ("restrict,pty ssh-rsa", "restrict,pty"),
+                (" ssh-rsa", ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn it_iterates_parsed_options() {
+        assert_that_cases(
+            |i| {
+                KeyOptionsIter {
+                    rest: options(i).unwrap().1,
+                }
+                .collect::<Vec<_>>()
+            },
+            as_expected,
             vec![
                 ("restrict", vec![("restrict", None)]),
-                ("command=\"uptime\"", vec![("command", Some("uptime"))]),
+                (
+                    "command=\"uptime\"",
+                    vec![("command", Some("uptime".into()))],
+                ),
                 (
                     "restrict,command=\"uptime \",no-agent-forwarding",
                     vec![
                         ("restrict", None),
-                        ("command", Some("uptime ")),
+                        ("command", Some("uptime ".into())),
                         ("no-agent-forwarding", None),
                     ],
                 ),
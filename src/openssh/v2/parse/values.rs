@@ -0,0 +1,479 @@
+use super::super::errors::{ForwardSpecError, PatternError, TimeError};
+use super::super::models::{
+    ExpiryTime, ForwardPort, ForwardSpec, HostPattern, HostPatternList, IpNetwork,
+};
+#[cfg(feature = "key_encoding")]
+use super::super::models::{Fingerprint, FingerprintError, HashAlg};
+#[cfg(feature = "key_encoding")]
+use data_encoding::BASE64_NOPAD;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Interpret a pattern as an address or CIDR block, as `sshd` does before
+/// falling back to wildcard matching. Patterns which are not addresses,
+/// or whose prefix is not a number up to 128, give `None`.
+fn network(pattern: &str) -> Result<Option<IpNetwork>, PatternError> {
+    let (address, prefix_len) = match pattern.find('/') {
+        Some(slash) => {
+            let prefix = &pattern[slash + 1..];
+
+            if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+                return Ok(None);
+            }
+            match prefix.parse::<u8>() {
+                Ok(prefix_len) if prefix_len <= 128 => (&pattern[..slash], Some(prefix_len)),
+                _ => return Ok(None),
+            }
+        }
+        None => (pattern, None),
+    };
+
+    let address = match address.parse::<IpAddr>() {
+        Ok(address) => address,
+        Err(_) => return Ok(None),
+    };
+    let max_len = if address.is_ipv4() { 32 } else { 128 };
+    let network = IpNetwork {
+        address,
+        prefix_len: prefix_len.unwrap_or(max_len),
+    };
+
+    if network.prefix_len > max_len || network.masked(address) != address {
+        return Err(PatternError::InconsistentMask(pattern.to_owned()));
+    }
+
+    Ok(Some(network))
+}
+
+impl FromStr for HostPatternList {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let patterns = s
+            .split(',')
+            .map(|entry| {
+                let negated = entry.starts_with('!');
+                let pattern = if negated { &entry[1..] } else { entry };
+
+                if pattern.is_empty() {
+                    return Err(PatternError::EmptyEntry);
+                }
+
+                Ok(HostPattern {
+                    negated,
+                    pattern: pattern.to_owned(),
+                    network: network(pattern)?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { patterns })
+    }
+}
+
+/// Longest host name `sshd` accepts in a forwarding permission.
+const MAX_HOST_LEN: usize = 1024;
+
+/// Split a forwarding permission into its host and port, as `sshd`'s
+/// `hpdelim2` does; the host may be in brackets, and is separated from
+/// the port by ":" or "/".
+fn host_and_port(spec: &str) -> Option<(&str, Option<&str>)> {
+    let host_end = if spec.starts_with('[') {
+        spec.find(']')? + 1
+    } else {
+        spec.find(|c| c == ':' || c == '/')
+            .unwrap_or_else(|| spec.len())
+    };
+    let (host, rest) = spec.split_at(host_end);
+
+    if rest.is_empty() {
+        Some((host, None))
+    } else if rest.starts_with(':') || rest.starts_with('/') {
+        Some((host, Some(&rest[1..])))
+    } else {
+        None
+    }
+}
+
+impl FromStr for ForwardSpec {
+    type Err = ForwardSpecError;
+
+    /// Parse a `permitopen` permission.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, port) = match host_and_port(s) {
+            Some((host, port)) if host.len() <= MAX_HOST_LEN => (host, port),
+            _ => return Err(ForwardSpecError::InvalidHost(s.to_owned())),
+        };
+        let host = if host.starts_with('[') && host.ends_with(']') {
+            &host[1..host.len() - 1]
+        } else {
+            host
+        };
+        let port = match port {
+            Some("*") => ForwardPort::Any,
+            Some(port) => match port.parse::<u16>() {
+                Ok(port) if port > 0 => ForwardPort::Port(port),
+                _ => return Err(ForwardSpecError::InvalidPort(s.to_owned())),
+            },
+            None => return Err(ForwardSpecError::InvalidPort(s.to_owned())),
+        };
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+        })
+    }
+}
+
+impl ForwardSpec {
+    /// Parse a `permitlisten` permission, which may also be a bare port
+    /// to permit listening on any address.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ForwardSpecError` if the host or port is invalid.
+    pub fn parse_listen(s: &str) -> Result<Self, ForwardSpecError> {
+        if s.contains(':') {
+            return s.parse();
+        }
+
+        format!("*:{}", s).parse().map_err(|err| match err {
+            ForwardSpecError::InvalidHost(_) => ForwardSpecError::InvalidHost(s.to_owned()),
+            ForwardSpecError::InvalidPort(_) => ForwardSpecError::InvalidPort(s.to_owned()),
+        })
+    }
+}
+
+/// Convert a (year, month, day) date from 1970 onwards to days since the
+/// Unix epoch. Days past the end of the month run on into the next, as
+/// with `mktime`.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // count months from March, so leap days fall at the end of the year
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+impl FromStr for ExpiryTime {
+    type Err = TimeError;
+
+    /// Parse a time as `sshd` does, also accepting `UTC` in place of `Z`
+    /// and either in lower case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        let (digits, utc) = if bytes.len() > 1 && bytes[bytes.len() - 1].eq_ignore_ascii_case(&b'Z')
+        {
+            (&s[..s.len() - 1], true)
+        } else if bytes.len() > 3 && bytes[bytes.len() - 3..].eq_ignore_ascii_case(b"UTC") {
+            (&s[..s.len() - 3], true)
+        } else {
+            (s, false)
+        };
+
+        if ![8, 12, 14].contains(&digits.len()) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(TimeError::InvalidFormat(s.to_owned()));
+        }
+
+        // fields missing from shorter times are zero
+        let field = |start: usize, end: usize| {
+            digits
+                .get(start..end)
+                .and_then(|field| field.parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        let (year, month, day) = (field(0, 4), field(4, 6), field(6, 8));
+        let (hour, minute, second) = (field(8, 10), field(10, 12), field(12, 14));
+
+        if year < 1970
+            || month < 1
+            || month > 12
+            || day < 1
+            || day > 31
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return Err(TimeError::OutOfRange(s.to_owned()));
+        }
+
+        Ok(Self {
+            unix_time: days_from_civil(year, month, day) * 86_400
+                + hour * 3600
+                + minute * 60
+                + second,
+            utc,
+        })
+    }
+}
+
+/// Fingerprints are written as `ssh-keygen -l` shows them. MD5
+/// fingerprints may also be written without the `MD5:` prefix, as older
+/// releases showed them.
+#[cfg(feature = "key_encoding")]
+impl FromStr for Fingerprint {
+    type Err = FingerprintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FingerprintError::InvalidDigest(s.to_owned());
+        let (alg, digest) = match s.find(':') {
+            Some(colon) if &s[..colon] == "SHA256" => (HashAlg::Sha256, &s[colon + 1..]),
+            Some(colon) if &s[..colon] == "MD5" => (HashAlg::Md5, &s[colon + 1..]),
+            Some(2) => (HashAlg::Md5, s),
+            _ => return Err(FingerprintError::UnknownAlgorithm(s.to_owned())),
+        };
+
+        let (digest, len) = match alg {
+            HashAlg::Md5 => (
+                digest
+                    .split(':')
+                    .map(|byte| match byte.len() {
+                        2 => u8::from_str_radix(byte, 16).map_err(|_| invalid()),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                16,
+            ),
+            HashAlg::Sha256 => (
+                BASE64_NOPAD
+                    .decode(digest.trim_end_matches('=').as_bytes())
+                    .map_err(|_| invalid())?,
+                32,
+            ),
+        };
+
+        if digest.len() == len {
+            Ok(Self { alg, digest })
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(address: &str, prefix_len: u8) -> Option<IpNetwork> {
+        Some(IpNetwork {
+            address: address.parse().unwrap(),
+            prefix_len,
+        })
+    }
+
+    #[test]
+    fn it_parses_networks() {
+        let cases = vec![
+            ("10.0.0.0/8", net("10.0.0.0", 8)),
+            ("10.1.2.3", net("10.1.2.3", 32)),
+            ("2001:db8::/32", net("2001:db8::", 32)),
+            ("::1", net("::1", 128)),
+            ("0.0.0.0/0", net("0.0.0.0", 0)),
+            ("10.0.0.*", None),
+            ("host.example.com", None),
+            ("10.0.0.0/", None),
+            ("10.0.0.0/x", None),
+            ("10.0.0.0/129", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(Ok(expected), network(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn it_rejects_inconsistent_masks() {
+        for input in &["10.1.2.3/8", "10.0.0.0/33", "2001:db8::1/32"] {
+            assert_eq!(
+                Err(PatternError::InconsistentMask((*input).to_owned())),
+                network(input)
+            );
+        }
+    }
+
+    #[test]
+    fn it_parses_pattern_lists() {
+        let subject = "*.example.com,!10.0.0.0/8".parse::<HostPatternList>();
+
+        assert_eq!(
+            Ok(HostPatternList {
+                patterns: vec![
+                    HostPattern {
+                        negated: false,
+                        pattern: "*.example.com".to_owned(),
+                        network: None,
+                    },
+                    HostPattern {
+                        negated: true,
+                        pattern: "10.0.0.0/8".to_owned(),
+                        network: net("10.0.0.0", 8),
+                    },
+                ],
+            }),
+            subject
+        );
+        assert_eq!("*.example.com,!10.0.0.0/8", subject.unwrap().to_string());
+    }
+
+    #[test]
+    fn it_rejects_empty_entries() {
+        for input in &["", "a,,b", "a,", "!"] {
+            assert_eq!(
+                Err(PatternError::EmptyEntry),
+                input.parse::<HostPatternList>()
+            );
+        }
+    }
+
+    fn spec(host: &str, port: ForwardPort) -> ForwardSpec {
+        ForwardSpec {
+            host: host.to_owned(),
+            port,
+        }
+    }
+
+    #[test]
+    fn it_parses_forward_specs() {
+        let cases = vec![
+            (
+                "example.com:443",
+                spec("example.com", ForwardPort::Port(443)),
+            ),
+            (
+                "example.com/443",
+                spec("example.com", ForwardPort::Port(443)),
+            ),
+            ("*:*", spec("*", ForwardPort::Any)),
+            (
+                "[2001:db8::1]:22",
+                spec("2001:db8::1", ForwardPort::Port(22)),
+            ),
+            ("[::1]/*", spec("::1", ForwardPort::Any)),
+            (":22", spec("", ForwardPort::Port(22))),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(Ok(expected), input.parse::<ForwardSpec>(), "{:?}", input);
+        }
+        assert_eq!(
+            Ok(spec("*", ForwardPort::Port(8080))),
+            ForwardSpec::parse_listen("8080")
+        );
+        assert_eq!(
+            Ok(spec("localhost", ForwardPort::Port(8080))),
+            ForwardSpec::parse_listen("localhost:8080")
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_forward_specs() {
+        for input in &["[::1:22", "[::1]x:22"] {
+            assert_eq!(
+                Err(ForwardSpecError::InvalidHost((*input).to_owned())),
+                input.parse::<ForwardSpec>()
+            );
+        }
+        for input in &[
+            "example.com",
+            "[::1]",
+            "::1:22",
+            "host:0",
+            "host:65536",
+            "host:",
+        ] {
+            assert_eq!(
+                Err(ForwardSpecError::InvalidPort((*input).to_owned())),
+                input.parse::<ForwardSpec>()
+            );
+        }
+        assert_eq!(
+            Err(ForwardSpecError::InvalidPort("http".to_owned())),
+            ForwardSpec::parse_listen("http")
+        );
+    }
+
+    fn time(unix_time: u64, utc: bool) -> ExpiryTime {
+        ExpiryTime { unix_time, utc }
+    }
+
+    #[test]
+    fn it_parses_expiry_times() {
+        let cases = vec![
+            ("19700101", time(0, false)),
+            ("20300101", time(1_893_456_000, false)),
+            ("20300101Z", time(1_893_456_000, true)),
+            ("203001011230", time(1_893_501_000, false)),
+            ("20300101123059utc", time(1_893_501_059, true)),
+            ("20240229", time(1_709_164_800, false)),
+            ("20230231", time(1_677_801_600, false)),
+            ("21000301", time(4_107_542_400, false)),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(Ok(expected), input.parse::<ExpiryTime>(), "{:?}", input);
+        }
+        assert_eq!("20230303", time(1_677_801_600, false).to_string());
+        assert_eq!("20300101123059Z", time(1_893_501_059, true).to_string());
+    }
+
+    #[test]
+    fn it_rejects_invalid_expiry_times() {
+        for input in &[
+            "2030010",
+            "2030-01-01",
+            "203001011",
+            "Z",
+            "20300101 ",
+            "\u{ff12}0300101",
+        ] {
+            assert_eq!(
+                Err(TimeError::InvalidFormat((*input).to_owned())),
+                input.parse::<ExpiryTime>()
+            );
+        }
+        for input in &[
+            "19691231",
+            "20301301",
+            "20300100",
+            "203001012400",
+            "203001011260",
+        ] {
+            assert_eq!(
+                Err(TimeError::OutOfRange((*input).to_owned())),
+                input.parse::<ExpiryTime>()
+            );
+        }
+    }
+
+    #[cfg(feature = "key_encoding")]
+    #[test]
+    fn it_parses_fingerprints() {
+        let md5 = "MD5:2b:84:41:f7:47:2c:0c:69:08:2c:df:66:eb:3d:41:50";
+        let sha256 = "SHA256:khAC3AmpVGUK3vjBz9U9Yd5Zzy8rPjkTCHkZ/vTZXTA";
+
+        for fingerprint in &[md5, sha256] {
+            assert_eq!(
+                *fingerprint,
+                fingerprint.parse::<Fingerprint>().unwrap().to_string()
+            );
+        }
+        assert_eq!(md5.parse::<Fingerprint>(), md5[4..].parse::<Fingerprint>());
+        assert_eq!(
+            Err(FingerprintError::UnknownAlgorithm("SHA1:abc".to_owned())),
+            "SHA1:abc".parse::<Fingerprint>()
+        );
+        for invalid in &["MD5:2b:84", "MD5:2b:8:41", "SHA256:khAC3Amp", "SHA256:!!!!"] {
+            assert_eq!(
+                Err(FingerprintError::InvalidDigest((*invalid).to_owned())),
+                invalid.parse::<Fingerprint>()
+            );
+        }
+    }
+}
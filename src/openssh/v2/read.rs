@@ -0,0 +1,189 @@
+use super::errors::ParseError;
+use super::models::KeysFileLine;
+use super::parse::keys_file_line;
+use std::io::{BufRead, ErrorKind};
+
+/// Reads an `authorized_keys` file one line at a time, without holding
+/// the whole file in memory.
+///
+/// A line which is not valid UTF-8 is reported as an `Io` error and
+/// reading carries on with the next line. Any other I/O error ends the
+/// iteration once it has been reported.
+///
+/// ```
+/// use authorized_keys::openssh::v2::{KeysFile, KeysFileReader};
+///
+/// let input: &[u8] = b"# comment\nssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM\n";
+///
+/// for line in KeysFileReader::new(input) {
+///     println!("{:?}", line.expect("that was a valid line!"));
+/// }
+///
+/// let file = KeysFileReader::new(input)
+///     .collect::<Result<KeysFile, _>>()
+///     .expect("that was a valid authorized_keys file!");
+/// ```
+#[derive(Debug)]
+pub struct KeysFileReader<R> {
+    reader: R,
+    buffer: String,
+    line_no: usize,
+    done: bool,
+}
+
+impl<R: BufRead> KeysFileReader<R> {
+    /// Create a reader for the `authorized_keys` data in `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            line_no: 0,
+            done: false,
+        }
+    }
+}
+
+/// Remove the line ending from a line, as `str::lines` does.
+fn without_line_ending(line: &str) -> &str {
+    if line.ends_with('\n') {
+        let line = &line[..line.len() - 1];
+
+        if line.ends_with('\r') {
+            &line[..line.len() - 1]
+        } else {
+            line
+        }
+    } else {
+        line
+    }
+}
+
+impl<R: BufRead> Iterator for KeysFileReader<R> {
+    type Item = Result<KeysFileLine, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        self.buffer.clear();
+        self.line_no += 1;
+
+        match self.reader.read_line(&mut self.buffer) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => {
+                let mut line = without_line_ending(&self.buffer);
+
+                if self.line_no == 1 && line.starts_with('\u{feff}') {
+                    line = &line['\u{feff}'.len_utf8()..];
+                }
+
+                Some(keys_file_line(self.line_no, line))
+            }
+            Err(err) => {
+                // the invalid line has been consumed, but after any other
+                // error the reader may fail the same way forever
+                self.done = err.kind() != ErrorKind::InvalidData;
+
+                Some(Err(ParseError::Io {
+                    line: self.line_no,
+                    kind: err.kind(),
+                    message: err.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::errors::Expected;
+    use super::super::models::{Comment, KeysFile};
+    use super::*;
+    use std::io::{self, Read};
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(ErrorKind::Other, "disk on fire"))
+        }
+    }
+
+    impl BufRead for FailingReader {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Err(io::Error::new(ErrorKind::Other, "disk on fire"))
+        }
+
+        fn consume(&mut self, _: usize) {}
+    }
+
+    #[test]
+    fn it_reads_lines() {
+        let input: &[u8] =
+            b"# comment\r\n\nssh-ed25519 foobar==\nno-pty ssh-ed25519 foo!bar\nssh-rsa foobar==";
+        let lines = KeysFileReader::new(input).collect::<Vec<_>>();
+
+        assert_eq!(5, lines.len());
+        assert_eq!(
+            Ok(KeysFileLine::Comment(Comment::new(" comment".to_owned()))),
+            lines[0]
+        );
+        assert_eq!(Ok(KeysFileLine::Blank("".to_owned())), lines[1]);
+        assert!(lines[4].is_ok());
+        assert_eq!(
+            Err(ParseError::Syntax {
+                line: 4,
+                column: 23,
+                token: "!bar".to_owned(),
+                expected: Expected::Base64,
+            }),
+            lines[3]
+        );
+    }
+
+    #[test]
+    fn it_reads_the_same_lines_as_parsing() {
+        let input =
+            "\u{feff}# comment\n\nssh-ed25519 foobar== comment\r\nrestrict ssh-rsa foobar==\n";
+        let read = KeysFileReader::new(input.as_bytes())
+            .collect::<Result<KeysFile, _>>()
+            .unwrap();
+        let parsed = input.parse::<KeysFile>().unwrap();
+
+        assert_eq!(parsed.lines, read.lines);
+    }
+
+    #[test]
+    fn it_reports_read_errors() {
+        let input: &[u8] = b"# comment\n\xff\nssh-ed25519 foobar==\n";
+        let lines = KeysFileReader::new(input).collect::<Vec<_>>();
+
+        assert_eq!(3, lines.len());
+        assert!(lines[2].is_ok());
+
+        match &lines[1] {
+            Err(ParseError::Io { line, kind, .. }) => {
+                assert_eq!(2, *line);
+                assert_eq!(ErrorKind::InvalidData, *kind);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    #[test]
+    fn it_stops_after_an_io_error() {
+        let lines = KeysFileReader::new(FailingReader).collect::<Vec<_>>();
+
+        assert_eq!(1, lines.len());
+        match &lines[0] {
+            Err(ParseError::Io { line, kind, .. }) => {
+                assert_eq!(1, *line);
+                assert_eq!(ErrorKind::Other, *kind);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -1,7 +1,40 @@
 /// Generate an enum with mappings to and from the underlying string
 /// value
+///
+/// If a catch-all variant name is given after the values, any other
+/// string is kept in that variant, and parsing cannot fail.
 #[macro_export]
 macro_rules! string_enum {
+    (@values $enum_name:ident, { $( $name:ident ),+ }) => {
+        impl $enum_name {
+            /// Keys from the enum, in the order they were defined
+            #[must_use]
+            pub fn enum_values() -> Vec<Self> {
+                vec![
+                    $($enum_name::$name,)+
+                ]
+            }
+
+            /// String values for the keys in the enum, in the order
+            /// they were defined
+            #[must_use]
+            pub fn string_values() -> Vec<String> {
+                Self::enum_values()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            }
+
+            /// Keys and values from the enum, as paired tuples.
+            #[must_use]
+            pub fn name_value_pairs() -> Vec<(Self, String)> {
+                Self::enum_values()
+                    .into_iter()
+                    .zip(Self::string_values())
+                    .collect()
+            }
+        }
+    };
     ( $enum_name:ident, { $( $name:ident = $val:expr ),+ } ) => {
         #[derive(Eq, PartialEq, Clone, Debug)]
         pub enum $enum_name {
@@ -27,33 +60,43 @@ macro_rules! string_enum {
             }
         }
 
-        impl $enum_name {
-            /// Keys from the enum, in the order they were defined
-            #[must_use]
-            pub fn enum_values() -> Vec<Self> {
-                vec![
-                    $($enum_name::$name,)+
-                ]
+        $crate::string_enum!(@values $enum_name, { $( $name ),+ });
+    };
+    ( $enum_name:ident, { $( $name:ident = $val:expr ),+ }, $other:ident ) => {
+        #[derive(Eq, PartialEq, Clone, Debug)]
+        pub enum $enum_name {
+            $( $name, )+
+            /// Any other value, kept as it was written
+            $other(String),
+        }
+
+        impl<'a> From<&'a str> for $enum_name {
+            fn from(from_val: &'a str) -> Self {
+                match from_val {
+                    $( $val => $enum_name::$name, )+
+                    _ => $enum_name::$other(from_val.to_owned())
+                }
             }
+        }
 
-            /// String values for the keys in the enum, in the order
-            /// they were defined
-            #[must_use]
-            pub fn string_values() -> Vec<String> {
-                Self::enum_values()
-                    .iter()
-                    .map(ToString::to_string)
-                    .collect()
+        impl std::str::FromStr for $enum_name {
+            type Err = ();
+
+            fn from_str(from_val: &str) -> Result<Self, Self::Err> {
+                Ok(Self::from(from_val))
             }
+        }
 
-            /// Keys and values from the enum, as paired tuples.
-            #[must_use]
-            pub fn name_value_pairs() -> Vec<(Self, String)> {
-                Self::enum_values()
-                    .into_iter()
-                    .zip(Self::string_values())
-                    .collect()
+        impl std::fmt::Display for $enum_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+                f.write_str(match self {
+                    $( $enum_name::$name => $val, )+
+                    $enum_name::$other(val) => val,
+                })
             }
         }
+
+        // the catch-all variant is left out of the listed values
+        $crate::string_enum!(@values $enum_name, { $( $name ),+ });
     };
 }
@@ -1,9 +1,44 @@
 #![allow(clippy::assertions_on_constants)]
 
+use crate::openssh::v2::KeyAuthorization;
 use spectral::result::ResultAssertions;
 use spectral::{assert_that, Spec};
 use std::fmt::Debug;
 
+#[cfg(feature = "key_encoding")]
+macro_rules! ed25519_data {
+    () => {
+        "AAAAC3NzaC1lZDI1NTE5AAAAIGgqo1o+dOHqeIc7A5MG53s5iYwpMQm7f3hnn+uxtHUM"
+    };
+}
+
+/// Data of an Ed25519 key, as written after its type.
+#[cfg(feature = "key_encoding")]
+pub(crate) const ED25519_DATA: &str = ed25519_data!();
+/// An Ed25519 key, without options or comments.
+#[cfg(feature = "key_encoding")]
+pub(crate) const ED25519: &str = concat!("ssh-ed25519 ", ed25519_data!());
+/// A DSA key, as generated by `ssh-keygen -t dsa`.
+#[cfg(feature = "key_encoding")]
+pub(crate) const DSA: &str = "ssh-dss AAAAB3NzaC1kc3MAAACBANRTqa/bGTt4a+gwyk8Owidk5Yub1W52x57UNuIN+KOLJyWbTxo3cCTQDkO1MBNSob7Lczjrmfe3rD5K4dc9Nx+bSa9HYAav483/fjB9yOi8dfm4B5pzB8/RJsey/OYmQmRW/bkNvRF64VP5IFEPZjhzRWalOJO1D1pPrqgJD3mLAAAAFQD8bgtWCThjm9gT3EiiKxuWiuf/UQAAAIEAvyNXBUP8NZB2M7TAia/XfYEnJpmnfXdrk3OM5+j+o9e5gMFym9IjehnRjNhT9e49Nb84E150iGsUXp80LbkOqYsNAh5I6rkJHjOb+HfJ79pvpCeW9xwnP9tyNPXSCc3NLZyzlvZn0x4I1DcbKYzx8drE9662GWotJUkkg3RNLr0AAACBAIf90OIfxibD/5M3A/hpbCJwcRGc+55Hco/icvI8u21dxCffzJ06YV8ctHXvdKaNZJZhCoNxebccl4g6qc2VDYtPtdIE88nTvw3zuzfdccYUfF4a6a0WbpL3x+sWLTupHJqQnU+gxQtcWnUWSsbfNoT91bjkIPyoRvyhvguAFHcV";
+/// An ECDSA key on the NIST P-384 curve.
+#[cfg(feature = "key_encoding")]
+pub(crate) const ECDSA_384: &str = "ecdsa-sha2-nistp384 AAAAE2VjZHNhLXNoYTItbmlzdHAzODQAAAAIbmlzdHAzODQAAABhBClzGIK+h+pDeaErvj1om+ZoHwFio8O95J51sa9/aC1UXDzMv+ltH6mjB+8uHq7BqAeReN0i0iLnIl+DNR/saX5PHk7asgdAHJA0J++ZIbG6ffsWG1wgIVAcYSncev14rg==";
+/// A 1024 bit RSA key.
+#[cfg(feature = "key_encoding")]
+pub(crate) const RSA_1024: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAgQCrpukmdJ9FizcFu6WFin8Y53fAKKfDpBIUj9esOVGZOQB1VS60s5drEfIOygwu9Iec3wJBvlyZexh3KSU44QvTr0bCsuNmwxQerX83v6AcN3forEt0TCQxTxzwciBRSzZwkR9jNFGFlUSXNqqZzljEH72wAI+ab3JzWiFXAjGUzw==";
+/// A 2048 bit RSA key.
+#[cfg(feature = "key_encoding")]
+pub(crate) const RSA_2048: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCiGR0mC4YmvUXaQxTqXT39FsBAM+A5uqvXalHZjsXYhjp0rNGZFyxkZOUJnamP/2org3SEH2TXlk6tWjo91dB1VSMkUUuksEsETKvbzbswX+l72oZzgKJsCrGYn208Ww1/bW4nsgarqE40l+8u0ggII0pUWHL8EJJvgJTEpi2vgc2PRMfvw6WVCF1UYgOB8SbclbCI6/9mgEfH4esT4Kurfu77a9aZbQcDY4lBECMow1MHzBVZP6CwoIeXsk36ujiFKbmT3XwQswSNWaJePnuvQxfWSMW/N1qo59MP1csaJGSwq2DmQjTghOgWdABbN3tcQWsZybh3Nt2vDIflEjXR";
+/// A user certificate for `RSA_1024`, signed by an ECDSA P-384 key.
+#[cfg(feature = "key_encoding")]
+pub(crate) const RSA_1024_CERT: &str = "ssh-rsa-cert-v01@openssh.com AAAAHHNzaC1yc2EtY2VydC12MDFAb3BlbnNzaC5jb20AAAAg0R/TtRx2LUGGT1z+1yTF6HwO1HU8DY+1nPNgwmgBNzIAAAADAQABAAAAgQCrpukmdJ9FizcFu6WFin8Y53fAKKfDpBIUj9esOVGZOQB1VS60s5drEfIOygwu9Iec3wJBvlyZexh3KSU44QvTr0bCsuNmwxQerX83v6AcN3forEt0TCQxTxzwciBRSzZwkR9jNFGFlUSXNqqZzljEH72wAI+ab3JzWiFXAjGUzwAAAAAAAAAAAAAAAQAAAAJpZAAAAAkAAAAFYWxpY2UAAAAAAAAAAP//////////AAAAAAAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAAIgAAAATZWNkc2Etc2hhMi1uaXN0cDM4NAAAAAhuaXN0cDM4NAAAAGEEKXMYgr6H6kN5oSu+PWib5mgfAWKjw73knnWxr39oLVRcPMy/6W0fqaMH7y4ersGoB5F43SLSIuciX4M1H+xpfk8eTtqyB0AckDQn75khsbp9+xYbXCAhUBxhKdx6/XiuAAAAhAAAABNlY2RzYS1zaGEyLW5pc3RwMzg0AAAAaQAAADEAy0z/6fbnyFS6jwUtQDIPcZGVmxOwIkLFFdkdwX5kc7QGxQcy78AmfZIumXUHSopfAAAAMHD3a7vYhmZOSREjTQ4LkIbe4ejcb4FXojhSMwqO5UU2KAZoQdqgtaDjV13a8dq0RA==";
+
+/// Parse a key authorization line, panicking if it is invalid.
+pub(crate) fn parse_key(line: &str) -> KeyAuthorization {
+    line.parse().unwrap()
+}
+
 pub(crate) fn assert_that_cases<G, A, I, O>(test_fn: G, verification_fn: A, cases: Vec<(I, O)>)
 where
     G: Fn(I) -> O,